@@ -0,0 +1,113 @@
+//! Benchmarks of the explorer tree on a workspace of 100,000 files.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use floem::reactive::{create_rw_signal, SignalUpdate};
+use floem::views::VirtualVector;
+use mobius::file_explorer::data::{Node, Tree};
+use mobius::file_explorer::list::TreeView;
+
+use std::hint::black_box;
+use std::path::PathBuf;
+
+const DIRS: usize = 100;
+const FILES_PER_DIR: usize = 1000;
+
+/// A tree of `DIRS` directories with `FILES_PER_DIR` files each.
+fn workspace() -> Tree {
+    let mut tree = Tree::new(Node {
+        is_dir: true,
+        is_open: true,
+        ..Node::new("/var")
+    });
+    for dir in 0..DIRS {
+        tree.create(Node {
+            is_dir: true,
+            ..Node::new(format!("/var/maps{}", dir))
+        });
+        for file in 0..FILES_PER_DIR {
+            tree.create(Node::new(format!("/var/maps{}/MAP{:04}.wad", dir, file)));
+        }
+    }
+    tree
+}
+
+fn bench_tree(c: &mut Criterion) {
+    let tree = workspace();
+    let path = PathBuf::from(format!("/var/maps{}/MAP0500.wad", DIRS / 2));
+    let dir = tree.find(format!("/var/maps{}", DIRS / 2)).unwrap();
+
+    c.bench_function("create", |b| b.iter(workspace));
+    c.bench_function("find", |b| b.iter(|| tree.find(black_box(&path))));
+    c.bench_function("children", |b| {
+        b.iter(|| tree.children(black_box(dir)).count())
+    });
+    c.bench_function("clone", |b| b.iter(|| tree.clone()));
+    c.bench_function("remove", |b| {
+        b.iter_batched(
+            || tree.clone(),
+            |mut tree| tree.remove(dir),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("expand_to_depth", |b| {
+        b.iter_batched(
+            || tree.clone(),
+            |mut tree| tree.expand_to_depth(2),
+            BatchSize::LargeInput,
+        )
+    });
+
+    // a refresh where a directory was deleted and another created
+    let mut scan = tree.clone();
+    scan.remove(dir);
+    for file in 0..FILES_PER_DIR {
+        scan.create(Node::new(format!("/var/textures/TEX{:04}.png", file)));
+    }
+    c.bench_function("merge_from", |b| {
+        b.iter_batched(
+            || tree.clone(),
+            |mut tree| tree.merge_from(&scan),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_view(c: &mut Criterion) {
+    let mut tree = workspace();
+    tree.expand_to_depth(2);
+    let dir = tree.find(format!("/var/maps{}", DIRS / 2)).unwrap();
+    let tree = create_rw_signal(tree);
+    // a viewport of 40 rows in the middle of the list
+    let middle = DIRS * FILES_PER_DIR / 2;
+    let viewport = middle..middle + 40;
+
+    c.bench_function("view_slice", |b| {
+        b.iter(|| TreeView::new(tree).slice(viewport.clone()).count())
+    });
+    c.bench_function("view_scroll", |b| {
+        let mut view = TreeView::new(tree);
+        b.iter(|| {
+            let mut rows = 0;
+            for page in 0..10 {
+                let top = viewport.start + page * 40;
+                rows += view.slice(top..top + 40).count();
+            }
+            rows
+        })
+    });
+    c.bench_function("view_set_open", |b| {
+        let mut last = TreeView::new(tree);
+        let mut open = true;
+        b.iter(|| {
+            open = !open;
+            tree.update(|tree| tree.set_open(dir, open));
+            let mut view = TreeView::new(tree).reusing(&last);
+            let rows = view.slice(viewport.clone()).count();
+            last = view;
+            rows
+        })
+    });
+}
+
+criterion_group!(benches, bench_tree, bench_view);
+criterion_main!(benches);
@@ -1,46 +1,108 @@
 //! Application driver.
+//!
+//! ```text
+//! mobius <path> [second-path] [--new-window <path>]...
+//! ```
+//!
+//! Opens the project at `path`, and every project passed to `--new-window`
+//! in a window of its own.
 
-use floem::prelude::*;
+use floem::Application;
 
+use std::ffi::OsString;
 use std::path::PathBuf;
-use std::rc::Rc;
-
-use crate::file_explorer::{
-    data::{Node, Tree},
-    view::file_explorer_view,
-};
-
-pub fn app_view() -> impl IntoView {
-    let project_path = std::env::args_os()
-        .skip(1)
-        .next()
-        .map(|s| PathBuf::from(s))
-        .expect("arg1");
-
-    let mut tree = Tree::new(Node {
-        is_dir: true,
-        is_open: true,
-        ..Node::new(project_path.clone())
-    });
-
-    Rc::make_mut(tree.root_mut()).is_open = true;
-
-    for entry in walkdir::WalkDir::new(project_path.clone()) {
-        if let Ok(entry) = entry {
-            tree.create(Node {
-                is_dir: entry.file_type().is_dir(),
-                is_open: true,
-                ..Node::new(entry.into_path())
-            });
+
+use crate::workspace::{workspace_view, Workspace};
+
+/// The windows to open at launch.
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    root: PathBuf,
+    /// The path the second pane opens at, if not the project.
+    second_root: Option<PathBuf>,
+    /// Projects opened in windows of their own.
+    new_windows: Vec<PathBuf>,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Args, String> {
+        let mut paths = Vec::new();
+        let mut new_windows = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--new-window" {
+                let path = args.next().ok_or("--new-window needs a path")?;
+                new_windows.push(PathBuf::from(path));
+            } else {
+                paths.push(PathBuf::from(arg));
+            }
         }
+
+        let mut paths = paths.into_iter();
+        let root = paths.next().ok_or("missing project path")?;
+        let second_root = paths.next();
+        if paths.next().is_some() {
+            return Err("too many paths".to_owned());
+        }
+
+        Ok(Args {
+            root,
+            second_root,
+            new_windows,
+        })
     }
+}
 
-    let tree = create_rw_signal(tree);
+/// Opens the windows named on the command line.
+pub fn launch() {
+    let args = match Args::parse(std::env::args_os().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("mobius: {}", err);
+            eprintln!("usage: mobius <path> [second-path] [--new-window <path>]...");
+            std::process::exit(2);
+        }
+    };
+
+    // keep writing logs until the app exits
+    let _log_guard = crate::logging::init();
+    crate::locale::init();
+    crate::plugin::start();
+
+    let Args {
+        root,
+        second_root,
+        new_windows,
+    } = args;
+    let mut app = Application::new().window(
+        move |_| workspace_view(Workspace::new(root, second_root)),
+        None,
+    );
+    for root in new_windows {
+        app = app.window(move |_| workspace_view(Workspace::new(root, None)), None);
+    }
+    app.run();
+    crate::plugin::stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    container(file_explorer_view(tree)).style(|s| {
-        s.size(100.pct(), 100.pct())
-            .padding_vert(20.0)
-            .flex_col()
-            .items_center()
-    })
+    #[test]
+    fn test_parse_args() {
+        let args = |args: &[&str]| Args::parse(args.iter().map(OsString::from));
+
+        assert_eq!(
+            args(&["doom", "--new-window", "heretic", "freedoom"]),
+            Ok(Args {
+                root: "doom".into(),
+                second_root: Some("freedoom".into()),
+                new_windows: vec!["heretic".into()],
+            })
+        );
+        assert!(args(&[]).is_err());
+        assert!(args(&["doom", "--new-window"]).is_err());
+    }
 }
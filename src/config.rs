@@ -0,0 +1,270 @@
+//! User configuration files.
+//!
+//! Settings are read from `settings.toml` in the [config directory], and
+//! then from `.mobius/settings.toml` in the workspace, which overrides them:
+//!
+//! ```toml
+//! language = "fr"
+//!
+//! [explorer]
+//! sort = "modified"
+//! details = true
+//! flat = false
+//! hidden = ["target", "*.o"]
+//! protected = ["**/*.wad"]
+//! tools = ["slade", "gimp"]
+//! path_templates = ["{rel}:1"]
+//! preview_on_click = true
+//! ```
+//!
+//! `language` picks the [locale](crate::locale), and is only read from the
+//! user settings.
+//!
+//! [config directory]: config_dir
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::file_explorer::list::SortMode;
+
+/// The directory Mobius reads its configuration files from, if one can be
+/// found.
+///
+/// This is `mobius` inside `$XDG_CONFIG_HOME` (or `~/.config`) on Unix, and
+/// inside `%APPDATA%` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.map(|base| base.join("mobius"))
+}
+
+/// The templates new files can be created from, sorted by name.
+///
+/// Templates are the files in `templates` in the [config directory](config_dir).
+/// New files are named after the template they are created from.
+pub fn templates() -> Vec<PathBuf> {
+    let Some(dir) = config_dir().map(|dir| dir.join("templates")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut templates = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_file()))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    templates.sort();
+    templates
+}
+
+/// Settings for the file explorer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExplorerSettings {
+    /// The order siblings are listed in.
+    pub sort: SortMode,
+    /// Show the size and modified time of each node.
+    pub details: bool,
+    /// List every file in a flat list instead of a tree.
+    pub flat: bool,
+    /// Globs of nodes left out of the explorer.
+    pub hidden: Vec<String>,
+    /// Globs of paths, relative to the root, that need a typed confirmation
+    /// to delete or move.
+    pub protected: Vec<String>,
+    /// Programs offered to open files with, besides the default application.
+    pub tools: Vec<String>,
+    /// Formats offered by "Copy Path As", filled in by
+    /// [`format_path`](crate::file_explorer::ops::format_path).
+    pub path_templates: Vec<String>,
+    /// Preview files in a preview tab when they are clicked, instead of
+    /// only when they are double clicked.
+    pub preview_on_click: bool,
+}
+
+impl ExplorerSettings {
+    /// Loads the user settings, then the settings of the workspace at
+    /// `workspace` on top of them.
+    ///
+    /// Problems with the settings files are reported and skipped.
+    pub fn load(workspace: &Path) -> ExplorerSettings {
+        let mut settings = ExplorerSettings::default();
+
+        let paths = config_dir()
+            .map(|dir| dir.join("settings.toml"))
+            .into_iter()
+            .chain([workspace.join(".mobius").join("settings.toml")]);
+        for path in paths {
+            if let Err(err) = settings.load_file(&path) {
+                tracing::warn!(path = %path.display(), %err, "failed to load settings");
+            }
+        }
+
+        settings
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), Error> {
+        let s = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(Error::Io(err)),
+        };
+
+        for err in self.load_str(&s)? {
+            tracing::warn!(path = %path.display(), %err, "skipped setting");
+        }
+        Ok(())
+    }
+
+    /// Overrides the settings with the `[explorer]` table of a TOML config.
+    ///
+    /// Fails if the config is not valid TOML. Settings that cannot be parsed
+    /// are skipped, and returned as errors.
+    pub fn load_str(&mut self, s: &str) -> Result<Vec<Error>, Error> {
+        let doc = s
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| Error::Toml(err.to_string()))?;
+
+        let Some(explorer) = doc.get("explorer").and_then(|item| item.as_table_like()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut errors = Vec::new();
+        for (key, value) in explorer.iter() {
+            let result = match key {
+                "sort" => value
+                    .as_str()
+                    .and_then(SortMode::from_name)
+                    .map(|sort| self.sort = sort),
+                "details" => value.as_bool().map(|details| self.details = details),
+                "flat" => value.as_bool().map(|flat| self.flat = flat),
+                "hidden" => globs(value).map(|hidden| self.hidden = hidden),
+                "protected" => globs(value).map(|protected| self.protected = protected),
+                "tools" => strings(value).map(|tools| self.tools = tools),
+                "path_templates" => strings(value).map(|templates| self.path_templates = templates),
+                "preview_on_click" => value
+                    .as_bool()
+                    .map(|preview_on_click| self.preview_on_click = preview_on_click),
+                _ => {
+                    errors.push(Error::UnknownSetting(format!("explorer.{}", key)));
+                    continue;
+                }
+            };
+            if result.is_none() {
+                errors.push(Error::InvalidValue(format!("explorer.{}", key)));
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Compiles the [`ExplorerSettings::hidden`] globs.
+    pub fn hidden_globs(&self) -> globset::GlobSet {
+        compile(&self.hidden)
+    }
+}
+
+/// Reads an array of strings.
+fn strings(value: &toml_edit::Item) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|s| s.as_str().map(str::to_owned))
+        .collect()
+}
+
+/// Reads an array of valid globs.
+fn globs(value: &toml_edit::Item) -> Option<Vec<String>> {
+    let globs = strings(value)?;
+    for glob in &globs {
+        globset::Glob::new(glob).ok()?;
+    }
+    Some(globs)
+}
+
+/// Compiles a list of globs, skipping any that are invalid.
+fn compile(globs: &[String]) -> globset::GlobSet {
+    let mut builder = globset::GlobSetBuilder::new();
+    for glob in globs
+        .iter()
+        .filter_map(|glob| globset::Glob::new(glob).ok())
+    {
+        builder.add(glob);
+    }
+    builder.build().unwrap_or_default()
+}
+
+/// An error that can occur while loading a config file.
+#[derive(Debug)]
+pub enum Error {
+    /// The config file is not valid TOML.
+    Toml(String),
+    /// A setting does not exist.
+    UnknownSetting(String),
+    /// A setting has a value of the wrong type, or an invalid value.
+    InvalidValue(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Toml(err) => f.write_str(err),
+            Error::UnknownSetting(key) => write!(f, "unknown setting {}", key),
+            Error::InvalidValue(key) => write!(f, "invalid value for {}", key),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_str() {
+        let mut settings = ExplorerSettings::default();
+        let errors = settings
+            .load_str(
+                r#"
+                [explorer]
+                sort = "Modified"
+                flat = true
+                hidden = ["target", "*.o"]
+                protected = ["[invalid"]
+                tools = ["slade"]
+                preview_on_click = true
+                compact = true
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            settings,
+            ExplorerSettings {
+                sort: SortMode::Modified,
+                flat: true,
+                hidden: vec!["target".to_owned(), "*.o".to_owned()],
+                tools: vec!["slade".to_owned()],
+                preview_on_click: true,
+                ..ExplorerSettings::default()
+            }
+        );
+        assert!(matches!(
+            &errors[..],
+            [Error::InvalidValue(a), Error::UnknownSetting(b)]
+                if a == "explorer.protected" && b == "explorer.compact"
+        ));
+        assert!(settings.hidden_globs().is_match("target"));
+    }
+}
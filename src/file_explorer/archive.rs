@@ -0,0 +1,389 @@
+//! Archives browsed as if they were directories.
+//!
+//! The members of an archive at `doom.pk3` have paths like
+//! `doom.pk3/maps/MAP01.wad`. Members are read-only.
+
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read};
+use std::ops::{Bound, Range};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use super::data::{Tree, TreeIndex};
+use super::fs::{DirEntry, FsProvider, Metadata};
+
+/// The formats of archives that can be browsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// A zip archive, or a PK3.
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    /// Guesses the format of the archive at `path` from its name.
+    fn of(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Format::Tar)
+        } else if name.ends_with(".zip") || name.ends_with(".pk3") {
+            Some(Format::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Marks a node as a member of a browsed archive.
+///
+/// Members do not exist on the local disk, so they cannot be changed or
+/// handed to other programs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArchiveMember;
+
+/// Marks every node below the archive at `path` in `tree` as an
+/// [`ArchiveMember`].
+pub fn mark_members(tree: &mut Tree, path: &Path) {
+    let Some(ix) = tree.find(path) else {
+        return;
+    };
+
+    let mut stack = tree.children(ix).map(|(ix, _)| ix).collect::<Vec<_>>();
+    while let Some(ix) = stack.pop() {
+        stack.extend(tree.children(ix).map(|(ix, _)| ix));
+        tree.set_data(ix, ArchiveMember);
+    }
+}
+
+/// Checks if the node at `ix` in `tree` is an [`ArchiveMember`].
+pub fn is_member(tree: &Tree, ix: TreeIndex) -> bool {
+    tree.get(ix)
+        .is_some_and(|node| node.data::<ArchiveMember>().is_some())
+}
+
+/// Checks if `path` is named like an archive that can be browsed.
+pub fn is_archive(path: &Path) -> bool {
+    Format::of(path).is_some()
+}
+
+/// The members of an archive, as a read-only [`FsProvider`] rooted at the
+/// path of the archive.
+#[derive(Debug)]
+pub struct ArchiveFs {
+    path: PathBuf,
+    /// The archive, or the uncompressed tar of a `.tar.gz`.
+    data: Arc<[u8]>,
+    /// The directory of a zip archive, read once when it is opened.
+    zip: Option<zip::ZipArchive<Cursor<Arc<[u8]>>>>,
+    members: BTreeMap<PathBuf, Member>,
+}
+
+/// A member of an [`ArchiveFs`], and where to read it from.
+#[derive(Clone, Debug)]
+enum Member {
+    Dir,
+    /// A file in a zip archive, by index.
+    Zip {
+        index: usize,
+        len: u64,
+    },
+    /// A file stored as is in a tar archive, by its range in the data.
+    Tar(Range<usize>),
+}
+
+impl ArchiveFs {
+    /// Opens the archive at `path` on `fs`, reading it into memory.
+    ///
+    /// This reads the whole archive, so it should not be called on the UI
+    /// thread.
+    pub fn open(fs: &dyn FsProvider, path: &Path) -> io::Result<ArchiveFs> {
+        let format = Format::of(path).ok_or(io::ErrorKind::InvalidInput)?;
+        let data = fs.read(path)?;
+
+        let mut files = Vec::new();
+        let (data, zip) = match format {
+            Format::Zip => {
+                let data = Arc::<[u8]>::from(data);
+                let mut zip = zip::ZipArchive::new(Cursor::new(data.clone()))?;
+                for index in 0..zip.len() {
+                    let file = zip.by_index_raw(index)?;
+                    // skip names that would escape the archive
+                    let Some(name) = file.enclosed_name() else {
+                        continue;
+                    };
+                    let member = if file.is_dir() {
+                        Member::Dir
+                    } else {
+                        Member::Zip {
+                            index,
+                            len: file.size(),
+                        }
+                    };
+                    files.push((name, member));
+                }
+                (data, Some(zip))
+            }
+            Format::Tar | Format::TarGz => {
+                // members are read straight from the data, so decompress it
+                // once
+                let data = if format == Format::TarGz {
+                    let mut tar = Vec::new();
+                    flate2::read::GzDecoder::new(&data[..]).read_to_end(&mut tar)?;
+                    tar
+                } else {
+                    data
+                };
+                let data = Arc::<[u8]>::from(data);
+                let mut tar = tar::Archive::new(&data[..]);
+                for entry in tar.entries()? {
+                    let entry = entry?;
+                    let Some(name) = enclosed_name(&entry.path()?) else {
+                        continue;
+                    };
+                    let member = if entry.header().entry_type().is_dir() {
+                        Member::Dir
+                    } else {
+                        let start = entry.raw_file_position() as usize;
+                        Member::Tar(start..start + entry.size() as usize)
+                    };
+                    files.push((name, member));
+                }
+                (data, None)
+            }
+        };
+
+        let mut members = BTreeMap::new();
+        for (name, member) in files {
+            let name = path.join(name);
+            // archives may leave out their directories
+            for ancestor in name.ancestors().skip(1) {
+                if ancestor == path {
+                    break;
+                }
+                members.insert(ancestor.to_owned(), Member::Dir);
+            }
+            members.entry(name).or_insert(member);
+        }
+
+        Ok(ArchiveFs {
+            path: path.to_owned(),
+            data,
+            zip,
+            members,
+        })
+    }
+
+    /// Finds the member at `path`. The archive itself is a directory.
+    fn member(&self, path: &Path) -> io::Result<&Member> {
+        if path == self.path {
+            return Ok(&Member::Dir);
+        }
+        self.members
+            .get(path)
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+/// Cleans up the name of a tar member, or returns `None` if it would escape
+/// the archive.
+fn enclosed_name(name: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => enclosed.push(part),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    (!enclosed.as_os_str().is_empty()).then_some(enclosed)
+}
+
+impl FsProvider for ArchiveFs {
+    fn entry(&self, path: &Path) -> io::Result<DirEntry> {
+        Ok(DirEntry {
+            path: path.to_owned(),
+            is_dir: matches!(self.member(path)?, Member::Dir),
+            link_target: None,
+        })
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        if !self.entry(path)?.is_dir {
+            return Err(io::ErrorKind::NotADirectory.into());
+        }
+
+        // every directory is a member, so the first member after `path` is
+        // one of its children, if it has any
+        let mut entries = Vec::new();
+        let mut from = Bound::Excluded(path.to_owned());
+        while let Some((member, kind)) = self.members.range((from, Bound::Unbounded)).next() {
+            if !member.starts_with(path) {
+                break;
+            }
+            entries.push(DirEntry {
+                path: member.clone(),
+                is_dir: matches!(kind, Member::Dir),
+                link_target: None,
+            });
+
+            // skip the members below it, which all sort before `name\0`
+            let mut next = member.clone().into_os_string();
+            next.push("\0");
+            from = Bound::Included(PathBuf::from(next));
+        }
+        Ok(entries)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.member(path)? {
+            Member::Dir => Err(io::ErrorKind::IsADirectory.into()),
+            Member::Zip { index, .. } => {
+                // clones share the directory that was already read
+                let mut zip = self.zip.clone().expect("zip member of a zip archive");
+                let mut file = zip.by_index(*index)?;
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)?;
+                Ok(buf)
+            }
+            Member::Tar(range) => Ok(self.data[range.clone()].to_owned()),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let len = match self.member(path)? {
+            Member::Dir => 0,
+            Member::Zip { len, .. } => *len,
+            Member::Tar(range) => range.len() as u64,
+        };
+        Ok(Metadata {
+            len,
+            modified: None,
+            read_only: true,
+        })
+    }
+
+    fn write(&self, _path: &Path, _contents: &[u8]) -> io::Result<()> {
+        Err(io::ErrorKind::ReadOnlyFilesystem.into())
+    }
+
+    fn create_dir(&self, _path: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::ReadOnlyFilesystem.into())
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::ReadOnlyFilesystem.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_explorer::data::Node;
+    use crate::file_explorer::fs::MemoryFs;
+
+    #[test]
+    fn test_zip() {
+        let mut data = Cursor::new(Vec::new());
+        let mut zip = zip::ZipWriter::new(&mut data);
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("maps/MAP01.wad", options).unwrap();
+        std::io::Write::write_all(&mut zip, b"PWAD").unwrap();
+        zip.start_file("MAPINFO", options).unwrap();
+        zip.finish().unwrap();
+
+        let fs = MemoryFs::new();
+        fs.create_dir_all("/doom");
+        fs.write(Path::new("/doom/doom.pk3"), data.get_ref())
+            .unwrap();
+
+        let pk3 = Path::new("/doom/doom.pk3");
+        let archive = ArchiveFs::open(&fs, pk3).unwrap();
+        let walked = archive
+            .walk(pk3)
+            .map(|entry| entry.unwrap())
+            .map(|entry| (entry.path, entry.is_dir))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            walked,
+            [
+                (pk3.to_owned(), true),
+                (pk3.join("MAPINFO"), false),
+                (pk3.join("maps"), true),
+                (pk3.join("maps/MAP01.wad"), false),
+            ]
+        );
+        assert_eq!(archive.read(&pk3.join("maps/MAP01.wad")).unwrap(), b"PWAD");
+        assert_eq!(
+            archive.metadata(&pk3.join("maps/MAP01.wad")).unwrap().len,
+            4
+        );
+        assert!(archive.write(&pk3.join("MAPINFO"), b"").is_err());
+    }
+
+    #[test]
+    fn test_tar_gz() {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, contents) in [
+            ("maps/MAP01.wad", &b"PWAD"[..]),
+            ("maps/e1/MAP02.wad", b"PWAD 2"),
+            ("maps.txt", b"MAP01"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, contents).unwrap();
+        }
+        let data = tar.into_inner().unwrap().finish().unwrap();
+
+        let fs = MemoryFs::new();
+        fs.create_dir_all("/doom");
+        fs.write(Path::new("/doom/maps.tar.gz"), &data).unwrap();
+
+        let tgz = Path::new("/doom/maps.tar.gz");
+        let archive = ArchiveFs::open(&fs, tgz).unwrap();
+        let listed = archive
+            .list_dir(&tgz.join("maps"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect::<Vec<_>>();
+        assert_eq!(listed, [tgz.join("maps/MAP01.wad"), tgz.join("maps/e1")]);
+        assert_eq!(
+            archive.read(&tgz.join("maps/e1/MAP02.wad")).unwrap(),
+            b"PWAD 2"
+        );
+        assert_eq!(archive.read(&tgz.join("maps.txt")).unwrap(), b"MAP01");
+    }
+
+    #[test]
+    fn test_mark_members() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/doom")
+        });
+        tree.create(Node::new("/doom/doom.pk3/maps/MAP01.wad"));
+        tree.create(Node::new("/doom/MAPINFO"));
+        mark_members(&mut tree, Path::new("/doom/doom.pk3"));
+
+        let member = |path| is_member(&tree, tree.find(path).unwrap());
+        assert!(member("/doom/doom.pk3/maps"));
+        assert!(member("/doom/doom.pk3/maps/MAP01.wad"));
+        assert!(!member("/doom/doom.pk3"));
+        assert!(!member("/doom/MAPINFO"));
+    }
+
+    #[test]
+    fn test_enclosed_name() {
+        assert_eq!(
+            enclosed_name(Path::new("./maps/MAP01.wad")),
+            Some(PathBuf::from("maps/MAP01.wad"))
+        );
+        assert_eq!(enclosed_name(Path::new("../MAP01.wad")), None);
+        assert_eq!(enclosed_name(Path::new("/etc/passwd")), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Counts shown as badges on directories.
+//!
+//! A node's own badges, like whether it is modified or how many problems it
+//! has, are attached to it as [node data](super::data::NodeData). Every
+//! directory also keeps the sum of the badges below it, which is updated up
+//! the parent chain whenever a node's badges change.
+
+use std::collections::HashSet;
+use std::ops::{Add, Sub};
+use std::path::PathBuf;
+
+use super::data::{Node, Tree, TreeIndex};
+
+/// Counts shown as badges on a node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Badges {
+    /// The number of modified files.
+    pub modified: usize,
+    /// The number of problems, like diagnostics.
+    pub problems: usize,
+}
+
+impl Badges {
+    /// Checks if there is nothing to show.
+    pub fn is_empty(&self) -> bool {
+        *self == Badges::default()
+    }
+}
+
+impl Add for Badges {
+    type Output = Badges;
+
+    fn add(self, other: Badges) -> Badges {
+        Badges {
+            modified: self.modified + other.modified,
+            problems: self.problems + other.problems,
+        }
+    }
+}
+
+impl Sub for Badges {
+    type Output = Badges;
+
+    /// Stops at zero, since sums are stale until they are
+    /// [recounted](recount) after nodes move.
+    fn sub(self, other: Badges) -> Badges {
+        Badges {
+            modified: self.modified.saturating_sub(other.modified),
+            problems: self.problems.saturating_sub(other.problems),
+        }
+    }
+}
+
+/// The badges of a node itself.
+struct Own(Badges);
+
+/// The sum of the badges of every node below a node.
+struct Below(Badges);
+
+/// Gets the badges of `node`, including every node below it.
+pub fn get(node: &Node) -> Badges {
+    own(node) + below(node)
+}
+
+fn own(node: &Node) -> Badges {
+    node.data::<Own>().map_or_else(Badges::default, |own| own.0)
+}
+
+fn below(node: &Node) -> Badges {
+    node.data::<Below>()
+        .map_or_else(Badges::default, |below| below.0)
+}
+
+/// Sets the badges of the node at `ix`, updating the sums of the nodes above
+/// it.
+pub fn set(tree: &mut Tree, ix: TreeIndex, badges: Badges) {
+    let Some(node) = tree.get(ix) else {
+        return;
+    };
+    let old = own(node);
+    if old == badges {
+        return;
+    }
+
+    let mut parent = node.parent;
+    if badges.is_empty() {
+        tree.remove_data::<Own>(ix);
+    } else {
+        tree.set_data(ix, Own(badges));
+    }
+
+    while let Some(ix) = parent {
+        let Some(node) = tree.get(ix) else {
+            break;
+        };
+        parent = node.parent;
+        let sum = below(node) - old + badges;
+        tree.set_data(ix, Below(sum));
+    }
+}
+
+/// Marks the nodes at `paths` as modified, and every other node as not,
+/// keeping their problems. Paths not in the tree are ignored.
+pub fn set_modified(tree: &mut Tree, paths: &[PathBuf]) {
+    let modified = paths
+        .iter()
+        .filter_map(|path| tree.find(path))
+        .collect::<HashSet<_>>();
+    let changed = tree
+        .iter()
+        .filter(|(ix, node)| (own(node).modified > 0) != modified.contains(ix))
+        .map(|(ix, node)| (ix, own(node)))
+        .collect::<Vec<_>>();
+
+    for (ix, badges) in changed {
+        let modified = usize::from(modified.contains(&ix));
+        set(tree, ix, Badges { modified, ..badges });
+    }
+}
+
+/// Recomputes the sums of every node, for after nodes with badges were
+/// removed or moved.
+pub fn recount(tree: &mut Tree) {
+    let counted = tree
+        .iter()
+        .filter(|(_, node)| node.data::<Below>().is_some())
+        .map(|(ix, _)| ix)
+        .collect::<Vec<_>>();
+    for ix in counted {
+        tree.remove_data::<Below>(ix);
+    }
+
+    let badges = tree
+        .iter()
+        .filter_map(|(ix, node)| Some((ix, node.data::<Own>()?.0)))
+        .collect::<Vec<_>>();
+    for (ix, badges) in badges {
+        let mut parent = tree.get(ix).and_then(|node| node.parent);
+        while let Some(ix) = parent {
+            let Some(node) = tree.get(ix) else {
+                break;
+            };
+            parent = node.parent;
+            let sum = below(node) + badges;
+            tree.set_data(ix, Below(sum));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roll_up() {
+        let badges = |modified, problems| Badges { modified, problems };
+
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        let doom2 = tree.create(Node::new("/var/games/doom/doom2.wad")).unwrap();
+        let heretic = tree.create(Node::new("/var/games/heretic.wad")).unwrap();
+        let games = tree.find("/var/games").unwrap();
+
+        set(&mut tree, doom2, badges(1, 2));
+        set(&mut tree, heretic, badges(1, 0));
+        assert_eq!(get(tree.root()), badges(2, 2));
+
+        set(&mut tree, doom2, badges(1, 0));
+        assert_eq!(get(tree.get(games).unwrap()), badges(2, 0));
+
+        tree.remove(heretic);
+        recount(&mut tree);
+        assert_eq!(get(tree.root()), badges(1, 0));
+    }
+
+    #[test]
+    fn test_set_modified() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        let doom2 = tree.create(Node::new("/var/games/doom2.wad")).unwrap();
+        tree.create(Node::new("/var/games/heretic.wad"));
+        let games = tree.find("/var/games").unwrap();
+        set(
+            &mut tree,
+            doom2,
+            Badges {
+                modified: 0,
+                problems: 1,
+            },
+        );
+
+        let changed = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        set_modified(
+            &mut tree,
+            &changed(&["/var/games/doom2.wad", "/var/games/heretic.wad", "/opt"]),
+        );
+        assert_eq!(get(tree.get(games).unwrap()).modified, 2);
+
+        set_modified(&mut tree, &changed(&["/var/games/heretic.wad"]));
+        assert_eq!(
+            get(tree.root()),
+            Badges {
+                modified: 1,
+                problems: 1
+            }
+        );
+    }
+}
@@ -1,13 +1,71 @@
 //! File explorer tree.
 
+use std::any::{Any, TypeId};
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::fs::{FsProvider, LocalFs, Metadata};
 
 /// A file explorer tree.
+///
+/// Nodes live in an arena, linked to their parent, first child and siblings
+/// by index. Every change bumps the [revision](Tree::revision) of the tree,
+/// so views built from it can tell when they are stale, and catch up with
+/// [`Tree::changes_since`].
 #[derive(Clone, Debug)]
 pub struct Tree {
-    arena: im::Vector<Rc<Node>>,
+    slots: Vec<Slot>,
+    free: Vec<NonZeroUsize>,
+    /// The index of every node, by path.
+    paths: HashMap<PathBuf, TreeIndex>,
+    revision: u64,
+    /// The changes that led to the current revision, oldest first.
+    changes: VecDeque<Change>,
+}
+
+/// A change to a [`Tree`] that leaves its nodes where they are, as listed
+/// by [`Tree::changes_since`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The node was opened or closed.
+    Open(TreeIndex),
+    /// The metadata or data of the node changed.
+    Node(TreeIndex),
+}
+
+/// The open state of a [`Tree`], by path relative to its root, so it can be
+/// saved and restored across sessions.
+///
+/// ```toml
+/// open = ["maps", "maps/episode1"]
+/// selected = "maps/episode1/MAP01.wad"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeState {
+    /// The open directories, sorted.
+    pub open: Vec<PathBuf>,
+    /// The selected node, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<PathBuf>,
+}
+
+/// The number of changes a [`Tree`] remembers.
+const MAX_CHANGES: usize = 1024;
+
+/// A slot in the [`Tree`] arena.
+#[derive(Clone, Debug)]
+struct Slot {
+    /// Incremented every time the slot is freed, so stale [`TreeIndex`]es
+    /// can be detected.
+    generation: u32,
+    node: Option<Node>,
 }
 
 impl Tree {
@@ -16,170 +74,566 @@ impl Tree {
     /// The base path is automatically inferred to be a directory.
     pub fn new(base: Node) -> Tree {
         // create root node @ index zero
-        let mut arena = im::Vector::new();
-        arena.push_back(Rc::new(base));
-        Tree { arena }
+        let paths = HashMap::from([(base.path.clone(), TreeIndex::ROOT)]);
+        Tree {
+            slots: vec![Slot {
+                generation: 0,
+                node: Some(Node {
+                    parent: None,
+                    first_child: None,
+                    next_sibling: None,
+                    prev_sibling: None,
+                    children_open_count: 0,
+                    ..base
+                }),
+            }],
+            free: Vec::new(),
+            paths,
+            revision: 0,
+            changes: VecDeque::new(),
+        }
     }
 
     /// Gets the root node.
-    pub fn root(&self) -> &Rc<Node> {
+    pub fn root(&self) -> &Node {
         self.get(TreeIndex::ROOT).expect("tree must have root node")
     }
 
-    /// Gets the root node mutably.
-    pub fn root_mut(&mut self) -> &mut Rc<Node> {
-        self.get_mut(TreeIndex::ROOT)
-            .expect("tree must have root node")
+    /// Counts the changes made to the tree. Two snapshots of a tree with the
+    /// same revision are the same.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Lists the changes made since `revision`, oldest first.
+    ///
+    /// Returns `None` if nodes were created, removed or moved since, or if
+    /// the changes are too old to be remembered, in which case anything
+    /// built from the tree must be rebuilt.
+    pub fn changes_since(&self, revision: u64) -> Option<impl Iterator<Item = Change> + '_> {
+        let count = usize::try_from(self.revision.checked_sub(revision)?).ok()?;
+        let start = self.changes.len().checked_sub(count)?;
+        Some(self.changes.range(start..).copied())
+    }
+
+    /// The number of nodes in the tree, including the root.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Checks if the tree has no nodes besides the root.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 1
     }
 
     /// Gets a specific node in the tree.
-    pub fn get(&self, ix: TreeIndex) -> Option<&Rc<Node>> {
-        self.arena.get(ix.0.get() - 1)
+    ///
+    /// Returns `None` if the node has been removed, even if its slot has
+    /// since been reused by another node.
+    pub fn get(&self, ix: TreeIndex) -> Option<&Node> {
+        self.slots
+            .get(ix.slot.get() - 1)
+            .filter(|slot| slot.generation == ix.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    fn get_mut(&mut self, ix: TreeIndex) -> Option<&mut Node> {
+        self.slots
+            .get_mut(ix.slot.get() - 1)
+            .filter(|slot| slot.generation == ix.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+
+    /// Gets a node that must exist.
+    fn node_mut(&mut self, ix: TreeIndex) -> &mut Node {
+        self.get_mut(ix).expect("node to exist")
+    }
+
+    /// Iterates over every node in the tree, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (TreeIndex, &Node)> {
+        self.slots.iter().enumerate().filter_map(|(i, slot)| {
+            let ix = TreeIndex {
+                slot: NonZeroUsize::new(i + 1).unwrap(),
+                generation: slot.generation,
+            };
+            slot.node.as_ref().map(|node| (ix, node))
+        })
+    }
+
+    /// Iterates over the children of the node at `ix`, in no particular
+    /// order.
+    pub fn children(&self, ix: TreeIndex) -> Children<'_> {
+        Children {
+            tree: self,
+            next: self.get(ix).and_then(|node| node.first_child),
+        }
     }
 
-    /// Gets a specific node in the tree mutably.
-    pub fn get_mut(&mut self, ix: TreeIndex) -> Option<&mut Rc<Node>> {
-        self.arena.get_mut(ix.0.get() - 1)
+    /// Finds the node at `path`.
+    pub fn find(&self, path: impl AsRef<Path>) -> Option<TreeIndex> {
+        self.paths.get(path.as_ref()).copied()
     }
 
     /// Creates a node in the tree that is a child of an existing node.
     ///
-    /// Returns `None` if the path cannot be represented in the tree.
+    /// Any missing ancestors are created as closed directories. Returns
+    /// `None` if the path cannot be represented in the tree.
     pub fn create(&mut self, node: Node) -> Option<TreeIndex> {
-        let node = Rc::new(node);
+        node.path.strip_prefix(self.root().path()).ok()?;
 
-        let Ok(discriminator) = node.path.strip_prefix(self.root().path()) else {
-            return None;
+        // the root is an ancestor of every path, so this finds a parent
+        let mut missing = Vec::new();
+        let mut parent_ix = TreeIndex::ROOT;
+        for ancestor in node.path.ancestors() {
+            if let Some(ix) = self.find(ancestor) {
+                parent_ix = ix;
+                break;
+            }
+            missing.push(ancestor.to_owned());
+        }
+        if missing.is_empty() {
+            return Some(parent_ix);
+        }
+
+        self.restructure();
+        let mut node = Some(node);
+        for ancestor in missing.into_iter().rev() {
+            let child = match node.take_if(|node| node.path == ancestor) {
+                Some(node) => node,
+                None => Node {
+                    is_dir: true,
+                    ..Node::new(ancestor)
+                },
+            };
+            parent_ix = self.link(parent_ix, child);
+        }
+
+        Some(parent_ix)
+    }
+
+    /// Opens or closes a node, updating the
+    /// [`Node::children_open_count`] of every node above it.
+    pub fn set_open(&mut self, ix: TreeIndex, is_open: bool) {
+        let Some(node) = self.get_mut(ix) else {
+            return;
         };
-        let mut ancestors_rev = node
-            .path
-            .ancestors()
-            .take(discriminator.components().count())
-            .map(|a| self.root().path().join(a))
+        if node.is_open == is_open {
+            return;
+        }
+        node.is_open = is_open;
+
+        let delta = node.children_open_count as isize;
+        if let Some(parent) = node.parent {
+            self.propagate(parent, if is_open { delta } else { -delta });
+        }
+        self.record(Change::Open(ix));
+    }
+
+    /// Closes every node except the root.
+    pub fn collapse_all(&mut self) {
+        self.expand_to_depth(1);
+    }
+
+    /// Opens every node less than `depth` levels below the root, and closes
+    /// the rest. The root is always left open.
+    ///
+    /// Recomputes every [`Node::children_open_count`] in a single pass.
+    pub fn expand_to_depth(&mut self, depth: usize) {
+        self.set_open_where(|_, level| level < depth);
+    }
+
+    /// Opens every node `is_open` returns `true` for, given the node and how
+    /// many levels below the root it is, and closes the rest. The root is
+    /// always left open.
+    ///
+    /// Recomputes every [`Node::children_open_count`] in a single pass.
+    fn set_open_where(&mut self, is_open: impl Fn(&Node, usize) -> bool) {
+        self.restructure();
+
+        // set open state, collecting nodes in pre-order
+        let mut order = Vec::new();
+        let mut stack = vec![(TreeIndex::ROOT, 0)];
+        while let Some((ix, level)) = stack.pop() {
+            let Some(node) = self.get_mut(ix) else {
+                continue;
+            };
+            node.is_open = ix == TreeIndex::ROOT || is_open(node, level);
+
+            stack.extend(self.children(ix).map(|(ix, _)| (ix, level + 1)));
+            order.push(ix);
+        }
+
+        // children come after their parents, so count in reverse
+        for ix in order.into_iter().rev() {
+            let children_open_count = self
+                .children(ix)
+                .map(|(_, child)| child.visible_count())
+                .sum();
+            self.node_mut(ix).children_open_count = children_open_count;
+        }
+    }
+
+    /// Gets the open directories of the tree, with nothing selected.
+    pub fn state(&self) -> TreeState {
+        let root = self.root().path();
+        let mut open = self
+            .iter()
+            .filter(|(ix, node)| *ix != TreeIndex::ROOT && node.is_open)
+            .filter_map(|(_, node)| node.path.strip_prefix(root).ok())
+            .map(Path::to_owned)
             .collect::<Vec<_>>();
-        ancestors_rev.reverse();
-
-        let mut cur_ix = TreeIndex::ROOT;
-        for ancestor in ancestors_rev {
-            // get next node
-            match self.get(cur_ix).and_then(|n| n.children.get(&ancestor)) {
-                Some(ix) => cur_ix = *ix,
-                None => {
-                    // if it does not exist, create it
-                    let node_ix = if node.path == ancestor {
-                        self.push(node.clone())
-                    } else {
-                        let parent = Rc::new(Node {
-                            is_dir: true,
-                            ..Node::new(&ancestor)
-                        });
-                        self.push(parent)
-                    };
-
-                    // add ancestor to children
-                    let parent = Rc::make_mut(self.get_mut(cur_ix).expect("node exists"));
-                    parent.children.insert(ancestor, node_ix);
-                    cur_ix = node_ix;
-                }
+        open.sort();
+
+        TreeState {
+            open,
+            selected: None,
+        }
+    }
+
+    /// Opens the directories listed in `state`, and closes the rest. Paths
+    /// that are not in the tree are ignored.
+    pub fn restore_state(&mut self, state: &TreeState) {
+        let root = self.root().path().to_owned();
+        let open = state
+            .open
+            .iter()
+            .map(|path| root.join(path))
+            .collect::<HashSet<_>>();
+        self.set_open_where(|node, _| open.contains(&node.path));
+    }
+
+    /// Opens every ancestor of the node at `path`, so it is visible.
+    ///
+    /// Returns the index of the node, or `None` if it does not exist.
+    pub fn reveal(&mut self, path: impl AsRef<Path>) -> Option<TreeIndex> {
+        let ix = self.find(path)?;
+
+        let mut cur_ix = self.get(ix)?.parent;
+        while let Some(ix) = cur_ix {
+            self.set_open(ix, true);
+            cur_ix = self.get(ix).and_then(|n| n.parent);
+        }
+
+        Some(ix)
+    }
+
+    /// Reconciles the tree with a fresh scan of the same directory.
+    ///
+    /// Nodes missing from `scan` are removed, and new nodes are inserted as
+    /// they are in `scan`. Nodes in both keep their open state, so a refresh
+    /// does not collapse the tree.
+    pub fn merge_from(&mut self, scan: &Tree) {
+        self.restructure();
+
+        let missing = self
+            .iter()
+            .filter(|(ix, node)| *ix != TreeIndex::ROOT && scan.find(node.path()).is_none())
+            .map(|(ix, _)| ix)
+            .collect::<Vec<_>>();
+        for ix in missing {
+            // does nothing if an ancestor was already removed
+            self.remove(ix);
+        }
+
+        // visit parents before their children, so new directories keep the
+        // open state they were scanned with
+        let mut stack = scan
+            .children(TreeIndex::ROOT)
+            .map(|(ix, _)| ix)
+            .collect::<Vec<_>>();
+        while let Some(scan_ix) = stack.pop() {
+            let Some(node) = scan.get(scan_ix) else {
+                continue;
+            };
+            stack.extend(scan.children(scan_ix).map(|(ix, _)| ix));
+
+            let Some(ix) = self.find(node.path()) else {
+                self.create(node.clone());
+                continue;
+            };
+
+            let existing = self.node_mut(ix);
+            existing.is_dir = node.is_dir;
+            existing.link_target = node.link_target.clone();
+            existing.fs = node.fs.clone();
+            // the file may have changed since it was last read
+            existing.metadata = OnceCell::new();
+        }
+    }
+
+    /// Forgets the metadata of a node, so it is read again.
+    pub fn reload_metadata(&mut self, ix: TreeIndex) {
+        if let Some(node) = self.get_mut(ix) {
+            node.metadata = OnceCell::new();
+            self.record(Change::Node(ix));
+        }
+    }
+
+    /// Attaches `value` to the node at `ix`, replacing its value of the same
+    /// type, if any.
+    pub fn set_data<T: Any + Send + Sync>(&mut self, ix: TreeIndex, value: T) {
+        if let Some(node) = self.get_mut(ix) {
+            node.data.insert(value);
+            self.record(Change::Node(ix));
+        }
+    }
+
+    /// Removes the value of type `T` from the node at `ix`.
+    pub fn remove_data<T: Any + Send + Sync>(&mut self, ix: TreeIndex) {
+        if let Some(node) = self.get_mut(ix) {
+            if node.data.remove::<T>() {
+                self.record(Change::Node(ix));
             }
         }
+    }
+
+    /// Removes a node and all of its children from the tree.
+    ///
+    /// Returns the removed nodes in pre-order, with their links cleared, so
+    /// they can be passed back to [`Tree::create`] to restore them. The root
+    /// node cannot be removed.
+    pub fn remove(&mut self, ix: TreeIndex) -> Option<Vec<Node>> {
+        if ix == TreeIndex::ROOT {
+            return None;
+        }
+
+        let node = self.get(ix)?;
+        let parent_ix = node.parent?;
+        let delta = node.visible_count();
+        self.restructure();
+
+        self.unlink(ix);
+        self.propagate(parent_ix, -(delta as isize));
+
+        // free the node and its children
+        let mut removed = Vec::new();
+        let mut stack = vec![ix];
+        while let Some(ix) = stack.pop() {
+            stack.extend(self.children(ix).map(|(ix, _)| ix));
 
-        // run updates
-        self.update_node(cur_ix);
+            let slot = &mut self.slots[ix.slot.get() - 1];
+            let Some(node) = slot.node.take() else {
+                continue;
+            };
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(ix.slot);
+            self.paths.remove(&node.path);
 
-        Some(cur_ix)
+            removed.push(Node {
+                parent: None,
+                first_child: None,
+                next_sibling: None,
+                prev_sibling: None,
+                children_open_count: 0,
+                ..node
+            });
+        }
+
+        Some(removed)
     }
 
-    /// Creates an unlinked node in the tree.
-    fn push(&mut self, node: Rc<Node>) -> TreeIndex {
-        let ix = self.arena.len();
-        self.arena.push_back(node);
-        TreeIndex(NonZeroUsize::new(ix + 1).unwrap())
+    /// Moves a node and all of its children to a new path.
+    ///
+    /// Returns `None` if the node does not exist or the new path cannot be
+    /// represented in the tree.
+    pub fn rename(&mut self, ix: TreeIndex, to: impl Into<PathBuf>) -> Option<TreeIndex> {
+        let to = to.into();
+        to.strip_prefix(self.root().path()).ok()?;
+
+        let from = self.get(ix)?.path.clone();
+        let removed = self.remove(ix)?;
+
+        let mut new_ix = None;
+        for node in removed {
+            let path = match node.path.strip_prefix(&from) {
+                Ok(rest) if rest.as_os_str().is_empty() => to.clone(),
+                Ok(rest) => to.join(rest),
+                Err(_) => continue,
+            };
+            let ix = self.create(Node { path, ..node });
+            new_ix = new_ix.or(ix);
+        }
+
+        new_ix
     }
 
-    /// Updates a node's [`Node::children_open_count`], cascading updating all
-    /// other nodes above it.
-    fn update_node(&mut self, ix: TreeIndex) {
-        let Some(node) = self.get(ix) else {
-            return;
+    /// Bumps the revision for `change`, remembering it.
+    fn record(&mut self, change: Change) {
+        if self.changes.len() == MAX_CHANGES {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(change);
+        self.revision += 1;
+    }
+
+    /// Bumps the revision for a change that creates, removes or moves
+    /// nodes, forgetting the changes before it.
+    fn restructure(&mut self) {
+        self.changes.clear();
+        self.revision += 1;
+    }
+
+    /// Adds `child` as the first child of the node at `parent_ix`.
+    fn link(&mut self, parent_ix: TreeIndex, child: Node) -> TreeIndex {
+        let next_sibling = self.node_mut(parent_ix).first_child;
+        let child = Node {
+            parent: Some(parent_ix),
+            first_child: None,
+            next_sibling,
+            prev_sibling: None,
+            children_open_count: 0,
+            ..child
         };
+        let delta = child.visible_count();
+        let path = child.path.clone();
 
-        let discriminator = node
-            .path
-            .strip_prefix(self.root().path())
-            .expect("valid subnode");
-        let mut ancestors_rev = discriminator
-            .ancestors()
-            .take(discriminator.components().count())
-            .map(|a| self.root().path().join(a))
-            .collect::<Vec<_>>();
-        ancestors_rev.reverse();
-
-        // works way up the tree
-        for i in (0..ancestors_rev.len()).rev() {
-            // finds node
-            let mut cur_ix = TreeIndex::ROOT;
-            for ancestor in ancestors_rev.iter().take(i) {
-                // get next node
-                match self.get(cur_ix).and_then(|n| n.children.get(ancestor)) {
-                    Some(ix) => cur_ix = *ix,
-                    None => return,
-                }
-            }
+        let ix = self.push(child);
+        if let Some(next) = next_sibling {
+            self.node_mut(next).prev_sibling = Some(ix);
+        }
+        self.node_mut(parent_ix).first_child = Some(ix);
+        self.paths.insert(path, ix);
+        self.propagate(parent_ix, delta as isize);
+        ix
+    }
 
-            // update current node children_open_count
-            let mut children_open_count = 0;
-            // count all children
-            for ix in self.get(cur_ix).expect("node to exist").children.values() {
-                let Some(child) = self.get(*ix) else {
-                    continue;
-                };
-                // add one for node's existence
-                children_open_count += 1;
-                // add all children if node is open
-                if child.is_open {
-                    children_open_count += child.children_open_count;
+    /// Detaches the node at `ix` from its parent and siblings.
+    fn unlink(&mut self, ix: TreeIndex) {
+        let node = self.node_mut(ix);
+        let (parent, prev, next) = (node.parent, node.prev_sibling, node.next_sibling);
+        node.parent = None;
+        node.prev_sibling = None;
+        node.next_sibling = None;
+
+        match prev {
+            Some(prev) => self.node_mut(prev).next_sibling = next,
+            None => {
+                if let Some(parent) = parent {
+                    self.node_mut(parent).first_child = next;
                 }
             }
+        }
+        if let Some(next) = next {
+            self.node_mut(next).prev_sibling = prev;
+        }
+    }
+
+    /// Puts an unlinked node in a free slot.
+    fn push(&mut self, node: Node) -> TreeIndex {
+        if let Some(slot_ix) = self.free.pop() {
+            let slot = &mut self.slots[slot_ix.get() - 1];
+            slot.node = Some(node);
+            return TreeIndex {
+                slot: slot_ix,
+                generation: slot.generation,
+            };
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            node: Some(node),
+        });
+        TreeIndex {
+            slot: NonZeroUsize::new(self.slots.len()).unwrap(),
+            generation: 0,
+        }
+    }
 
-            // update node
-            let node = Rc::make_mut(self.get_mut(cur_ix).expect("node to exist"));
-            node.children_open_count = children_open_count;
+    /// Adds `delta` to the [`Node::children_open_count`] of the node at `ix`,
+    /// cascading up the parent chain until a closed node is reached.
+    fn propagate(&mut self, ix: TreeIndex, delta: isize) {
+        let mut cur_ix = Some(ix);
+        while let Some(ix) = cur_ix {
+            let node = self.node_mut(ix);
+            node.children_open_count = node
+                .children_open_count
+                .checked_add_signed(delta)
+                .expect("children_open_count in range");
+
+            // a closed node's visible count does not change
+            cur_ix = if node.is_open { node.parent } else { None };
         }
     }
 }
 
+/// An iterator over the children of a node, created by [`Tree::children`].
+pub struct Children<'a> {
+    tree: &'a Tree,
+    next: Option<TreeIndex>,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = (TreeIndex, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ix = self.next?;
+        let node = self.tree.get(ix)?;
+        self.next = node.next_sibling;
+        Some((ix, node))
+    }
+}
+
 /// An index into a [`Tree`].
 ///
-/// Represents a [`Node`] in a tree.
-#[repr(transparent)]
+/// Represents a [`Node`] in a tree. Indices stay valid across tree
+/// mutations; once the node is removed, the index will never refer to
+/// another node.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct TreeIndex(NonZeroUsize);
+pub struct TreeIndex {
+    slot: NonZeroUsize,
+    generation: u32,
+}
 
 impl TreeIndex {
-    // SAFETY: this number is 1
-    pub const ROOT: TreeIndex = unsafe { TreeIndex(NonZeroUsize::new_unchecked(1)) };
+    pub const ROOT: TreeIndex = TreeIndex {
+        slot: NonZeroUsize::new(1).unwrap(),
+        generation: 0,
+    };
 }
 
 /// A file explorer node.
+///
+/// The links to other nodes are kept up to date by the [`Tree`], and are
+/// ignored when a node is passed to [`Tree::create`].
 #[derive(Clone, Debug)]
 pub struct Node {
     pub path: PathBuf,
+    pub parent: Option<TreeIndex>,
+    /// The first child of the node. Children are linked through their
+    /// siblings in no particular order.
+    pub first_child: Option<TreeIndex>,
+    pub next_sibling: Option<TreeIndex>,
+    pub prev_sibling: Option<TreeIndex>,
     pub is_dir: bool,
     pub is_open: bool,
-    pub children: im::HashMap<PathBuf, TreeIndex>,
+    /// The target of the node, if it is a symlink.
+    pub link_target: Option<PathBuf>,
     pub children_open_count: usize,
+    /// The provider the node was read from, which its metadata is read from.
+    pub fs: Arc<dyn FsProvider>,
+    /// The metadata of the node, read the first time it is needed.
+    pub metadata: OnceCell<Option<Metadata>>,
+    /// Values attached to the node by features built on the explorer.
+    pub data: NodeData,
 }
 
 impl Node {
-    /// Creates a new `Node` representing the entity @ `path`.
+    /// Creates a new `Node` representing the entity @ `path` on the local
+    /// disk.
     pub fn new(path: impl Into<PathBuf>) -> Node {
         Node {
             path: path.into(),
+            parent: None,
+            first_child: None,
+            next_sibling: None,
+            prev_sibling: None,
             is_dir: false,
             is_open: false,
-            children: im::HashMap::new(),
+            link_target: None,
             children_open_count: 0,
+            fs: LocalFs::shared(),
+            metadata: OnceCell::new(),
+            data: NodeData::default(),
         }
     }
 
@@ -187,30 +641,96 @@ impl Node {
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Checks if the node is a symlink.
+    pub fn is_symlink(&self) -> bool {
+        self.link_target.is_some()
+    }
+
+    /// The metadata of the node, or `None` if it cannot be read.
+    ///
+    /// Only the first call reads from the provider of the node.
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| self.fs.metadata(&self.path).ok())
+            .as_ref()
+    }
+
+    /// The value of type `T` attached to the node, if any.
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data.get()
+    }
+
+    /// Checks if the node cannot be written to.
+    ///
+    /// Reads the metadata of the node, if it has not been read yet.
+    pub fn is_read_only(&self) -> bool {
+        self.metadata().is_some_and(|metadata| metadata.read_only)
+    }
+
+    /// The number of rows this node takes up in its parent, including
+    /// itself.
+    fn visible_count(&self) -> usize {
+        if self.is_open {
+            self.children_open_count + 1
+        } else {
+            1
+        }
+    }
 }
 
 impl PartialEq for Node {
+    /// Compares everything but the provider, metadata and attached data.
     fn eq(&self, other: &Self) -> bool {
-        if self.path == other.path
+        self.path == other.path
+            && self.parent == other.parent
+            && self.first_child == other.first_child
+            && self.next_sibling == other.next_sibling
+            && self.prev_sibling == other.prev_sibling
             && self.is_dir == other.is_dir
             && self.is_open == other.is_open
+            && self.link_target == other.link_target
             && self.children_open_count == other.children_open_count
-        {
-            // check children
-            for (k, v) in self.children.iter() {
-                if let Some(other_v) = other.children.get(k) {
-                    if v != other_v {
-                        return false;
-                    }
-                } else {
-                    return false;
-                }
-            }
+    }
+}
 
-            true
-        } else {
-            false
-        }
+/// Values attached to a [`Node`], at most one of each type.
+///
+/// Lets features like git status or diagnostics keep their own state on
+/// nodes, without the tree knowing about them. Values are kept when the node
+/// is renamed or the tree is refreshed.
+#[derive(Clone, Default)]
+pub struct NodeData {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl NodeData {
+    /// Gets the value of type `T`, if any.
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Sets the value of type `T`, replacing the old one.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Removes the value of type `T`, returning `true` if there was one.
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> bool {
+        self.values.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Checks if no values are attached.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for NodeData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeData")
+            .field("len", &self.values.len())
+            .finish()
     }
 }
 
@@ -218,6 +738,13 @@ impl PartialEq for Node {
 mod tests {
     use super::*;
 
+    fn ix(slot: usize) -> TreeIndex {
+        TreeIndex {
+            slot: NonZeroUsize::new(slot).unwrap(),
+            generation: 0,
+        }
+    }
+
     #[test]
     fn test_update_node() {
         let mut tree = Tree::new(Node {
@@ -226,7 +753,7 @@ mod tests {
             ..Node::new("/var")
         });
 
-        Rc::make_mut(tree.root_mut()).is_open = true;
+        tree.set_open(TreeIndex::ROOT, true);
 
         tree.create(Node {
             is_dir: true,
@@ -257,31 +784,308 @@ mod tests {
         tree.create(Node::new("/var/games"));
         tree.create(Node::new("/var/games/secret"));
 
+        // children are linked newest first
         assert_eq!(
-            tree.arena
+            tree.slots
                 .into_iter()
-                .map(|c| Rc::unwrap_or_clone(c))
+                .filter_map(|slot| slot.node)
                 .collect::<Vec<_>>(),
             vec![
                 Node {
                     is_dir: true,
+                    first_child: Some(ix(3)),
                     children_open_count: 2,
-                    children: im::hashmap! {
-                        PathBuf::from("/var/opt") => TreeIndex(NonZeroUsize::new(2).unwrap()),
-                        PathBuf::from("/var/games") => TreeIndex(NonZeroUsize::new(3).unwrap()),
-                    },
                     ..Node::new("/var")
                 },
-                Node::new("/var/opt"),
                 Node {
-                    children: im::hashmap! {
-                        PathBuf::from("/var/games/secret") => TreeIndex(NonZeroUsize::new(4).unwrap()),
-                    },
+                    parent: Some(TreeIndex::ROOT),
+                    prev_sibling: Some(ix(3)),
+                    ..Node::new("/var/opt")
+                },
+                Node {
+                    parent: Some(TreeIndex::ROOT),
+                    first_child: Some(ix(4)),
+                    next_sibling: Some(ix(2)),
                     children_open_count: 1,
                     ..Node::new("/var/games")
                 },
-                Node::new("/var/games/secret"),
+                Node {
+                    parent: Some(ix(3)),
+                    ..Node::new("/var/games/secret")
+                },
             ]
         );
     }
+
+    #[test]
+    fn test_remove_node() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var/games")
+        });
+        tree.create(Node::new("/var/games/spelunky"));
+        tree.create(Node::new("/var/opt"));
+
+        let games = tree.find("/var/games").unwrap();
+        let removed = tree.remove(games).unwrap();
+
+        assert_eq!(
+            removed.iter().map(|n| n.path()).collect::<Vec<_>>(),
+            vec![Path::new("/var/games"), Path::new("/var/games/spelunky")]
+        );
+        assert_eq!(tree.find("/var/games"), None);
+        assert_eq!(tree.find("/var/games/spelunky"), None);
+        assert_eq!(tree.root().children_open_count, 1);
+
+        // freed slots are reused, but stale indices stay invalid
+        let ix = tree.create(Node::new("/var/lib")).unwrap();
+        assert!(ix.slot.get() <= 4);
+        assert_ne!(ix.generation, 0);
+        assert!(tree.get(games).is_none());
+        assert_eq!(tree.root().children_open_count, 2);
+    }
+
+    #[test]
+    fn test_rename_node() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var/games")
+        });
+        tree.create(Node::new("/var/games/spelunky"));
+
+        let ix = tree
+            .rename(tree.find("/var/games").unwrap(), "/var/fun")
+            .unwrap();
+
+        assert_eq!(tree.find("/var/fun"), Some(ix));
+        assert!(tree.get(ix).unwrap().is_open);
+        assert!(tree.find("/var/fun/spelunky").is_some());
+        assert_eq!(tree.find("/var/games"), None);
+        assert_eq!(tree.root().children_open_count, 2);
+    }
+
+    #[test]
+    fn test_node_data() {
+        #[derive(Debug, PartialEq)]
+        struct Diagnostics(usize);
+
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        let ix = tree.create(Node::new("/var/doom2.wad")).unwrap();
+        tree.set_data(ix, Diagnostics(3));
+        tree.set_data(ix, "modified");
+        assert_eq!(tree.get(ix).unwrap().data(), Some(&Diagnostics(3)));
+
+        // kept across a rename
+        let ix = tree.rename(ix, "/var/tnt.wad").unwrap();
+        assert_eq!(tree.get(ix).unwrap().data(), Some(&"modified"));
+
+        tree.remove_data::<Diagnostics>(ix);
+        assert_eq!(tree.get(ix).unwrap().data::<Diagnostics>(), None);
+        assert!(!tree.get(ix).unwrap().data.is_empty());
+    }
+
+    #[test]
+    fn test_tree_state() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/heretic.wad"));
+        tree.create(Node::new("/var/textures/wall.png"));
+        tree.reveal("/var/games/doom/doom2.wad");
+
+        let state = tree.state();
+        assert_eq!(
+            state,
+            TreeState {
+                open: vec![PathBuf::from("games"), PathBuf::from("games/doom")],
+                selected: None,
+            }
+        );
+
+        let s = toml_edit::ser::to_string(&state).unwrap();
+        let state = toml_edit::de::from_str::<TreeState>(&s).unwrap();
+
+        tree.collapse_all();
+        tree.set_open(tree.find("/var/textures").unwrap(), true);
+        tree.restore_state(&state);
+        assert_eq!(tree.state(), state);
+        assert_eq!(tree.root().children_open_count, 5);
+    }
+
+    #[test]
+    fn test_set_open() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/doom/tnt.wad"));
+
+        // intermediate directories are created closed
+        assert_eq!(tree.root().children_open_count, 1);
+
+        let games = tree.find("/var/games").unwrap();
+        let doom = tree.find("/var/games/doom").unwrap();
+        tree.set_open(doom, true);
+        assert_eq!(tree.get(games).unwrap().children_open_count, 3);
+        assert_eq!(tree.root().children_open_count, 1);
+
+        tree.set_open(games, true);
+        assert_eq!(tree.root().children_open_count, 4);
+
+        tree.set_open(doom, false);
+        assert_eq!(tree.root().children_open_count, 2);
+    }
+
+    #[test]
+    fn test_reveal() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/heretic.wad"));
+
+        let ix = tree.reveal("/var/games/doom/doom2.wad").unwrap();
+
+        assert!(!tree.get(ix).unwrap().is_open);
+        assert!(tree.get(tree.find("/var/games").unwrap()).unwrap().is_open);
+        assert!(
+            tree.get(tree.find("/var/games/doom").unwrap())
+                .unwrap()
+                .is_open
+        );
+        assert_eq!(tree.root().children_open_count, 4);
+        assert_eq!(tree.reveal("/var/opt"), None);
+    }
+
+    #[test]
+    fn test_merge_from() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/heretic.wad"));
+        tree.create(Node::new("/var/opt"));
+        tree.reveal("/var/games/doom/doom2.wad");
+
+        let mut scan = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        scan.create(Node::new("/var/games/doom/doom2.wad"));
+        scan.create(Node::new("/var/games/doom/tnt.wad"));
+        scan.create(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var/lib")
+        });
+        scan.create(Node::new("/var/lib/dpkg"));
+
+        tree.merge_from(&scan);
+
+        assert_eq!(tree.find("/var/games/heretic.wad"), None);
+        assert_eq!(tree.find("/var/opt"), None);
+        assert!(
+            tree.get(tree.find("/var/games/doom").unwrap())
+                .unwrap()
+                .is_open
+        );
+        assert!(tree.get(tree.find("/var/lib").unwrap()).unwrap().is_open);
+        assert!(tree.find("/var/games/doom/tnt.wad").is_some());
+        // games, doom, doom2.wad, tnt.wad, lib, dpkg
+        assert_eq!(tree.root().children_open_count, 6);
+    }
+
+    #[test]
+    fn test_expand_to_depth() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/heretic.wad"));
+        tree.create(Node::new("/var/opt"));
+
+        tree.expand_to_depth(usize::MAX);
+        assert_eq!(tree.root().children_open_count, 5);
+
+        tree.expand_to_depth(2);
+        assert_eq!(tree.root().children_open_count, 4);
+        assert!(
+            !tree
+                .get(tree.find("/var/games/doom").unwrap())
+                .unwrap()
+                .is_open
+        );
+
+        tree.collapse_all();
+        assert!(tree.root().is_open);
+        assert_eq!(tree.root().children_open_count, 2);
+        assert_eq!(
+            tree.get(tree.find("/var/games").unwrap())
+                .unwrap()
+                .children_open_count,
+            2
+        );
+    }
+
+    #[test]
+    fn test_changes_since() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        let ix = tree.create(Node::new("/var/games/doom2.wad")).unwrap();
+        let games = tree.find("/var/games").unwrap();
+        let revision = tree.revision();
+
+        tree.set_open(games, true);
+        tree.set_data(ix, "modified");
+        assert_eq!(
+            tree.changes_since(revision).unwrap().collect::<Vec<_>>(),
+            [Change::Open(games), Change::Node(ix)]
+        );
+        assert_eq!(tree.changes_since(tree.revision()).unwrap().count(), 0);
+
+        // the nodes moved, so earlier changes cannot be replayed
+        tree.create(Node::new("/var/games/tnt.wad"));
+        assert!(tree.changes_since(revision).is_none());
+
+        let revision = tree.revision();
+        for _ in 0..=MAX_CHANGES {
+            tree.reload_metadata(ix);
+        }
+        assert!(tree.changes_since(revision).is_none());
+        assert_eq!(
+            tree.changes_since(revision + 1).unwrap().count(),
+            MAX_CHANGES
+        );
+    }
 }
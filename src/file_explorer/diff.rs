@@ -0,0 +1,264 @@
+//! Side-by-side file comparisons.
+
+use floem::prelude::*;
+use similar::{DiffTag, TextDiff};
+
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::locale::tr_args;
+
+use super::fs::{FsProvider, LocalFs};
+use super::preview::message;
+
+/// The height of a row in the diff view.
+const ROW_HEIGHT: f64 = 16.0;
+/// The longest a diff may take before it settles for a coarser result.
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A row of a side-by-side diff, holding a line of each file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffRow {
+    pub tag: DiffTag,
+    /// The line of the old file, or `None` if the row was inserted.
+    pub old: Option<DiffLine>,
+    /// The line of the new file, or `None` if the row was deleted.
+    pub new: Option<DiffLine>,
+}
+
+/// A line in a [`DiffRow`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffLine {
+    /// The line number, starting at 1.
+    pub number: usize,
+    pub text: String,
+    /// The byte ranges of `text` that changed, if the line was replaced.
+    pub changed: Vec<Range<usize>>,
+}
+
+/// Diffs two texts line by line, aligning the lines side by side.
+///
+/// Replaced lines are paired up, and the characters that changed in each
+/// pair are highlighted.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffRow> {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(old, new);
+    let line = |lines: &[&str], i: usize| DiffLine {
+        number: i + 1,
+        text: lines[i].trim_end_matches(['\n', '\r']).to_owned(),
+        changed: Vec::new(),
+    };
+
+    let mut rows = Vec::new();
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        for i in 0..old_range.len().max(new_range.len()) {
+            let mut old = old_range.clone().nth(i).map(|i| line(diff.old_slices(), i));
+            let mut new = new_range.clone().nth(i).map(|i| line(diff.new_slices(), i));
+            if let (DiffTag::Replace, Some(old), Some(new)) = (tag, &mut old, &mut new) {
+                (old.changed, new.changed) = diff_chars(&old.text, &new.text);
+            }
+            rows.push(DiffRow { tag, old, new });
+        }
+    }
+    rows
+}
+
+/// Finds the byte ranges that changed between two lines, in each line.
+fn diff_chars(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_chars(old, new);
+    // the byte offset of each char, and of the end of the line
+    let offsets = |chars: &[&str]| {
+        std::iter::once(0)
+            .chain(chars.iter().scan(0, |offset, c| {
+                *offset += c.len();
+                Some(*offset)
+            }))
+            .collect::<Vec<_>>()
+    };
+    let (old_offsets, new_offsets) = (offsets(diff.old_slices()), offsets(diff.new_slices()));
+
+    let (mut old_changed, mut new_changed) = (Vec::new(), Vec::new());
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        if !old_range.is_empty() {
+            old_changed.push(old_offsets[old_range.start]..old_offsets[old_range.end]);
+        }
+        if !new_range.is_empty() {
+            new_changed.push(new_offsets[new_range.start]..new_offsets[new_range.end]);
+        }
+    }
+    (old_changed, new_changed)
+}
+
+/// A read-only, side-by-side comparison of the files at `old` and `new`.
+///
+/// Each row holds a line of both files, so they always scroll together.
+pub fn diff_view(old: PathBuf, new: PathBuf) -> impl IntoView {
+    diff_view_with(&LocalFs, old, new)
+}
+
+/// Like [`diff_view`], but reads the files from `fs`.
+pub fn diff_view_with(fs: &dyn FsProvider, old: PathBuf, new: PathBuf) -> impl IntoView {
+    let name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let title = tr_args(
+        "{old} ↔ {new} (Esc to close)",
+        &[("old", &name(&old)), ("new", &name(&new))],
+    );
+
+    let content = match (read_text(fs, &old), read_text(fs, &new)) {
+        (Ok(old), Ok(new)) => {
+            let rows = diff_lines(&old, &new)
+                .into_iter()
+                .enumerate()
+                .collect::<im::Vector<_>>();
+            virtual_list(
+                VirtualDirection::Vertical,
+                VirtualItemSize::Fixed(Box::new(|| ROW_HEIGHT)),
+                move || rows.clone(),
+                |(ix, _)| *ix,
+                |(_, row)| row_view(row),
+            )
+            .style(|s| {
+                s.flex_col()
+                    .width_full()
+                    .font_family("monospace".to_owned())
+            })
+            .into_any()
+        }
+        (Err(err), _) | (_, Err(err)) => {
+            message(tr_args("Cannot compare: {error}", &[("error", &err)])).into_any()
+        }
+    };
+
+    (
+        label(move || title.clone()).style(|s| s.padding(4.0).border_bottom(1.0).width_full()),
+        scroll(content).style(|s| s.flex_grow(1.0).min_height(0.0).width_full()),
+    )
+        .v_stack()
+        .style(|s| s.size_full().background(Color::WHITE))
+}
+
+/// Reads a text file to compare, failing if it is binary.
+fn read_text(fs: &dyn FsProvider, path: &Path) -> io::Result<String> {
+    let data = fs.read(path)?;
+    if data.contains(&0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            tr_args("{path} is a binary file", &[("path", &path.display())]),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+fn row_view(row: DiffRow) -> impl IntoView {
+    let changed = row.tag != DiffTag::Equal;
+    (
+        side_view(
+            row.old,
+            changed,
+            Color::rgb8(255, 235, 233),
+            Color::rgb8(255, 192, 192),
+        ),
+        side_view(
+            row.new,
+            changed,
+            Color::rgb8(230, 255, 237),
+            Color::rgb8(172, 242, 189),
+        ),
+    )
+        .h_stack()
+        .style(|s| s.height(ROW_HEIGHT).width_full())
+}
+
+/// One side of a row: the line number, then the line with its changed
+/// characters highlighted.
+fn side_view(
+    line: Option<DiffLine>,
+    changed: bool,
+    background: Color,
+    highlight: Color,
+) -> impl IntoView {
+    let number = line
+        .as_ref()
+        .map(|line| line.number.to_string())
+        .unwrap_or_default();
+
+    let mut segments = Vec::new();
+    if let Some(line) = &line {
+        let mut start = 0;
+        for range in &line.changed {
+            segments.push((line.text[start..range.start].to_owned(), false));
+            segments.push((line.text[range.clone()].to_owned(), true));
+            start = range.end;
+        }
+        segments.push((line.text[start..].to_owned(), false));
+    }
+    let text = h_stack_from_iter(segments.into_iter().map(move |(text, is_changed)| {
+        label(move || text.clone())
+            .style(move |s| s.apply_if(is_changed, |s| s.background(highlight)))
+    }));
+
+    (
+        label(move || number.clone()).style(|s| {
+            s.width(40.0)
+                .padding_right(6.0)
+                .justify_end()
+                .color(Color::GRAY)
+        }),
+        text,
+    )
+        .h_stack()
+        .style(move |s| {
+            s.width(50.pct())
+                .min_width(0.0)
+                .height_full()
+                .items_center()
+                .apply_if(changed && line.is_some(), |s| s.background(background))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let rows = diff_lines(
+            "map MAP01\nsky SKY1\nmusic D_RUNNIN\n",
+            "map MAP01\nsky SKY3\nmusic D_RUNNIN\nnext MAP02\n",
+        );
+
+        let tags = rows.iter().map(|row| row.tag).collect::<Vec<_>>();
+        assert_eq!(
+            tags,
+            [
+                DiffTag::Equal,
+                DiffTag::Replace,
+                DiffTag::Equal,
+                DiffTag::Insert
+            ]
+        );
+
+        let old = rows[1].old.as_ref().unwrap();
+        let new = rows[1].new.as_ref().unwrap();
+        assert_eq!((old.number, new.number), (2, 2));
+        assert_eq!(&old.text[old.changed[0].clone()], "1");
+        assert_eq!(&new.text[new.changed[0].clone()], "3");
+
+        assert!(rows[3].old.is_none());
+        assert_eq!(rows[3].new.as_ref().unwrap().number, 4);
+    }
+}
@@ -0,0 +1,153 @@
+//! Files and directories pinned above the tree.
+//!
+//! Favorites are kept per workspace in `.mobius/favorites.toml`, relative to
+//! the workspace where possible:
+//!
+//! ```toml
+//! paths = ["maps/MAP01.wad", "MAPINFO.txt"]
+//! ```
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::Error;
+
+/// Pinned paths, in the order they are shown.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Favorites {
+    paths: Vec<PathBuf>,
+}
+
+impl Favorites {
+    /// Loads the favorites of the workspace at `workspace`.
+    ///
+    /// Problems with the favorites file are reported, and no favorites are
+    /// loaded.
+    pub fn load(workspace: &Path) -> Favorites {
+        let path = favorites_path(workspace);
+        let s = match std::fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Favorites::default(),
+            Err(err) => {
+                tracing::warn!(path = %path.display(), %err, "failed to load favorites");
+                return Favorites::default();
+            }
+        };
+
+        Favorites::load_str(workspace, &s).unwrap_or_else(|err| {
+            tracing::warn!(path = %path.display(), %err, "failed to load favorites");
+            Favorites::default()
+        })
+    }
+
+    /// Reads favorites from a TOML string. Relative paths are relative to
+    /// `workspace`.
+    pub fn load_str(workspace: &Path, s: &str) -> Result<Favorites, Error> {
+        let doc = s
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| Error::Toml(err.to_string()))?;
+
+        let Some(paths) = doc.get("paths") else {
+            return Ok(Favorites::default());
+        };
+        let paths = paths
+            .as_array()
+            .and_then(|paths| {
+                paths
+                    .iter()
+                    .map(|path| Some(workspace.join(path.as_str()?)))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| Error::InvalidValue("paths".to_owned()))?;
+
+        Ok(Favorites { paths })
+    }
+
+    /// Saves the favorites of the workspace at `workspace`.
+    pub fn save(&self, workspace: &Path) -> io::Result<()> {
+        let path = favorites_path(workspace);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_toml(workspace))
+    }
+
+    /// Writes the favorites as TOML, relative to `workspace`.
+    pub fn to_toml(&self, workspace: &Path) -> String {
+        let mut paths = toml_edit::Array::new();
+        for path in &self.paths {
+            let path = path.strip_prefix(workspace).unwrap_or(path);
+            paths.push(path.to_string_lossy().as_ref());
+        }
+
+        let mut doc = toml_edit::DocumentMut::new();
+        doc["paths"] = toml_edit::value(paths);
+        doc.to_string()
+    }
+
+    /// The pinned paths, in order.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Checks if `path` is pinned.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.iter().any(|pinned| pinned == path)
+    }
+
+    /// Pins `path` at the end of the favorites, or unpins it if it is
+    /// already pinned.
+    pub fn toggle(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        match self.paths.iter().position(|pinned| *pinned == path) {
+            Some(index) => {
+                self.paths.remove(index);
+            }
+            None => self.paths.push(path),
+        }
+    }
+
+    /// Moves the pinned `path` to `index`, shifting the favorites after it.
+    pub fn move_to(&mut self, path: &Path, index: usize) {
+        let Some(from) = self.paths.iter().position(|pinned| pinned == path) else {
+            return;
+        };
+        let path = self.paths.remove(from);
+        self.paths.insert(index.min(self.paths.len()), path);
+    }
+}
+
+fn favorites_path(workspace: &Path) -> PathBuf {
+    workspace.join(".mobius").join("favorites.toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_favorites() {
+        let workspace = Path::new("/var/doom");
+        let mut favorites =
+            Favorites::load_str(workspace, r#"paths = ["MAPINFO.txt", "/var/games"]"#).unwrap();
+        assert_eq!(
+            favorites.paths(),
+            [
+                PathBuf::from("/var/doom/MAPINFO.txt"),
+                PathBuf::from("/var/games")
+            ]
+        );
+
+        favorites.toggle("/var/doom/maps");
+        favorites.toggle("/var/doom/MAPINFO.txt");
+        favorites.move_to(Path::new("/var/doom/maps"), 0);
+        assert_eq!(
+            favorites.paths(),
+            [PathBuf::from("/var/doom/maps"), PathBuf::from("/var/games")]
+        );
+
+        let saved = favorites.to_toml(workspace);
+        assert_eq!(Favorites::load_str(workspace, &saved).unwrap(), favorites);
+        assert!(Favorites::load_str(workspace, "paths = [1]").is_err());
+    }
+}
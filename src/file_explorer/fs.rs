@@ -0,0 +1,548 @@
+//! Filesystem backends.
+//!
+//! Trees are filled in from an [`FsProvider`]. [`LocalFs`] reads the local
+//! disk, and [`MemoryFs`] holds files in memory. Other providers can serve
+//! files from elsewhere, like a remote machine.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+
+/// A file or directory listed by an [`FsProvider`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// The target of the entry, if it is a symlink.
+    pub link_target: Option<PathBuf>,
+}
+
+/// The size, modified time and permissions of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub len: u64,
+    /// When the file was last modified, if the provider knows.
+    pub modified: Option<SystemTime>,
+    /// Whether the file cannot be written to.
+    pub read_only: bool,
+}
+
+/// A source of files and directories.
+pub trait FsProvider: fmt::Debug + Send + Sync {
+    /// Gets the entry at `path`.
+    fn entry(&self, path: &Path) -> io::Result<DirEntry>;
+
+    /// Gets the metadata of the file at `path`, following symlinks.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Lists the entries in the directory at `path`.
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+
+    /// Reads the contents of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Reads at most `len` bytes of the file at `path`, starting at `offset`.
+    ///
+    /// By default, the whole file is read with [`FsProvider::read`].
+    fn read_range(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let data = self.read(path)?;
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(len as usize).min(data.len());
+        Ok(data[start..end].to_owned())
+    }
+
+    /// Replaces the contents of the file at `path`, creating it if it does
+    /// not exist.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Creates a directory at `path`. Its parent must already exist.
+    fn create_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Moves the file or directory at `from` to `to`.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Copies the file at `from` to `to`, replacing `to` if it exists.
+    ///
+    /// By default, the file is read and written back.
+    fn copy_file(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write(to, &self.read(from)?)
+    }
+
+    /// Creates a symlink at `link` pointing to `target`.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the provider has no
+    /// symlinks, which is the default.
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        let _ = (target, link);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Moves the file or directory at `path` to the trash.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the provider has no
+    /// trash, which is the default.
+    fn trash(&self, path: &Path) -> io::Result<()> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Restores the file or directory most recently moved to the trash from
+    /// `path`.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the provider has no
+    /// trash, which is the default.
+    fn restore(&self, path: &Path) -> io::Result<()> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Lets the owner write to the file or directory at `path`.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the provider has no
+    /// permissions, which is the default.
+    fn set_writable(&self, path: &Path) -> io::Result<()> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Calls `on_change` with the path of every entry that changes below
+    /// `path`.
+    ///
+    /// Fails with [`io::ErrorKind::Unsupported`] if the provider cannot
+    /// watch for changes, which is the default.
+    fn watch(&self, path: &Path, on_change: Box<dyn Fn(&Path) + Send>) -> io::Result<()> {
+        let _ = (path, on_change);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Walks the entry at `path` and every entry below it, parents first.
+    ///
+    /// By default, directories are listed with [`FsProvider::list_dir`], and
+    /// symlinks are not followed.
+    fn walk<'a>(&'a self, path: &Path) -> Box<dyn Iterator<Item = io::Result<DirEntry>> + 'a> {
+        walk_links(self, path)
+    }
+}
+
+/// Walks the entry at `path` and every entry below it in `fs`, parents first.
+///
+/// Unlike [`FsProvider::walk`], symlinks are never followed, whatever the
+/// provider.
+pub fn walk_links<'a, F>(
+    fs: &'a F,
+    path: &Path,
+) -> Box<dyn Iterator<Item = io::Result<DirEntry>> + 'a>
+where
+    F: FsProvider + ?Sized,
+{
+    let mut stack = vec![fs.entry(path)];
+    Box::new(std::iter::from_fn(move || {
+        let entry = stack.pop()?;
+        if let Ok(entry) = &entry {
+            if entry.is_dir && entry.link_target.is_none() {
+                match fs.list_dir(&entry.path) {
+                    Ok(mut entries) => {
+                        // pop in order
+                        entries.sort_by(|a, b| b.path.cmp(&a.path));
+                        stack.extend(entries.into_iter().map(Ok));
+                    }
+                    Err(err) => stack.push(Err(err)),
+                }
+            }
+        }
+        Some(entry)
+    }))
+}
+
+/// The local disk.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalFs;
+
+impl LocalFs {
+    /// A shared handle to the local disk.
+    pub fn shared() -> Arc<dyn FsProvider> {
+        static SHARED: LazyLock<Arc<dyn FsProvider>> = LazyLock::new(|| Arc::new(LocalFs));
+        SHARED.clone()
+    }
+}
+
+impl FsProvider for LocalFs {
+    fn entry(&self, path: &Path) -> io::Result<DirEntry> {
+        let link_target = std::fs::symlink_metadata(path)?
+            .is_symlink()
+            .then(|| std::fs::read_link(path))
+            .transpose()?;
+
+        Ok(DirEntry {
+            path: path.to_owned(),
+            is_dir: path.is_dir(),
+            link_target,
+        })
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        std::fs::read_dir(path)?
+            .map(|entry| self.entry(&entry?.path()))
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Metadata {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            read_only: metadata.permissions().readonly(),
+        })
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    /// Reads only the range from disk.
+    fn read_range(&self, path: &Path, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+
+        let mut buf = Vec::new();
+        file.take(len).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    /// Keeps the permissions of the file.
+    fn copy_file(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::copy(from, to).map(drop)
+    }
+
+    #[cfg(unix)]
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    #[cfg(windows)]
+    fn symlink(&self, target: &Path, link: &Path) -> io::Result<()> {
+        // relative targets are resolved from the directory of the link
+        let is_dir = link
+            .parent()
+            .is_some_and(|parent| parent.join(target).is_dir());
+        if is_dir {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+
+    /// Moves the file or directory to the OS trash.
+    fn trash(&self, path: &Path) -> io::Result<()> {
+        trash::delete(path).map_err(io::Error::other)
+    }
+
+    #[cfg(any(
+        target_os = "windows",
+        all(
+            unix,
+            not(target_os = "macos"),
+            not(target_os = "ios"),
+            not(target_os = "android")
+        )
+    ))]
+    fn restore(&self, path: &Path) -> io::Result<()> {
+        // find the most recently trashed item at this path
+        let item = trash::os_limited::list()
+            .map_err(io::Error::other)?
+            .into_iter()
+            .filter(|item| item.original_path() == path)
+            .max_by_key(|item| item.time_deleted)
+            .ok_or_else(|| {
+                let message = format!("{} not found in the trash", path.display());
+                io::Error::new(io::ErrorKind::NotFound, message)
+            })?;
+
+        trash::os_limited::restore_all([item]).map_err(io::Error::other)
+    }
+
+    fn set_writable(&self, path: &Path) -> io::Result<()> {
+        let mut permissions = std::fs::metadata(path)?.permissions();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(permissions.mode() | 0o200);
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly(false);
+
+        std::fs::set_permissions(path, permissions)
+    }
+
+    /// Follows symlinks that stay below `path`. Symlinks that would cause a
+    /// cycle, or that point outside of `path`, are returned, but are not
+    /// followed.
+    fn walk<'a>(&'a self, path: &Path) -> Box<dyn Iterator<Item = io::Result<DirEntry>> + 'a> {
+        let root = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        let mut walker = walkdir::WalkDir::new(path).follow_links(true).into_iter();
+        Box::new(std::iter::from_fn(move || {
+            let entry = match walker.next()? {
+                Ok(entry) => entry,
+                Err(err) => return Some(loop_entry(&err).ok_or_else(|| err.into())),
+            };
+            if entry.path_is_symlink() && entry.file_type().is_dir() {
+                let target = entry.path().canonicalize();
+                if !target.is_ok_and(|target| target.starts_with(&root)) {
+                    walker.skip_current_dir();
+                }
+            }
+            Some(Ok(local_entry(entry)))
+        }))
+    }
+}
+
+/// Creates an entry for a walked file or directory.
+fn local_entry(entry: walkdir::DirEntry) -> DirEntry {
+    let link_target = if entry.path_is_symlink() {
+        std::fs::read_link(entry.path()).ok()
+    } else {
+        None
+    };
+
+    DirEntry {
+        is_dir: entry.file_type().is_dir(),
+        link_target,
+        path: entry.into_path(),
+    }
+}
+
+/// Creates an entry for a symlink that points to one of its own ancestors.
+fn loop_entry(err: &walkdir::Error) -> Option<DirEntry> {
+    let ancestor = err.loop_ancestor()?;
+    let path = err.path()?;
+
+    Some(DirEntry {
+        path: path.to_owned(),
+        is_dir: true,
+        link_target: Some(std::fs::read_link(path).unwrap_or_else(|_| ancestor.to_owned())),
+    })
+}
+
+/// Files and directories held in memory.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    /// The contents of each file, or `None` for directories.
+    entries: Mutex<Entries>,
+    /// The path of each trashed entry and the entries below it, most recently
+    /// trashed last.
+    trashed: Mutex<Vec<(PathBuf, Entries)>>,
+}
+
+type Entries = BTreeMap<PathBuf, Option<Vec<u8>>>;
+
+impl MemoryFs {
+    /// Creates a new, empty `MemoryFs`.
+    pub fn new() -> MemoryFs {
+        MemoryFs::default()
+    }
+
+    /// Creates a directory at `path`, and any missing ancestors.
+    pub fn create_dir_all(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let mut entries = self.entries.lock().unwrap();
+        for ancestor in path.ancestors() {
+            entries.entry(ancestor.to_owned()).or_insert(None);
+        }
+    }
+}
+
+impl FsProvider for MemoryFs {
+    fn entry(&self, path: &Path) -> io::Result<DirEntry> {
+        let entries = self.entries.lock().unwrap();
+        let contents = entries.get(path).ok_or(io::ErrorKind::NotFound)?;
+
+        Ok(DirEntry {
+            path: path.to_owned(),
+            is_dir: contents.is_none(),
+            link_target: None,
+        })
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(path) {
+            Some(None) => (),
+            Some(Some(_)) => return Err(io::ErrorKind::NotADirectory.into()),
+            None => return Err(io::ErrorKind::NotFound.into()),
+        }
+
+        Ok(entries
+            .iter()
+            .filter(|(child, _)| child.parent() == Some(path))
+            .map(|(child, contents)| DirEntry {
+                path: child.clone(),
+                is_dir: contents.is_none(),
+                link_target: None,
+            })
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let len = match self.entries.lock().unwrap().get(path) {
+            Some(Some(contents)) => contents.len() as u64,
+            Some(None) => 0,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+        Ok(Metadata {
+            len,
+            modified: None,
+            read_only: false,
+        })
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.entries.lock().unwrap().get(path) {
+            Some(Some(contents)) => Ok(contents.clone()),
+            Some(None) => Err(io::ErrorKind::IsADirectory.into()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let parent = path.parent().ok_or(io::ErrorKind::InvalidInput)?;
+        match entries.get(parent) {
+            Some(None) => (),
+            Some(Some(_)) => return Err(io::ErrorKind::NotADirectory.into()),
+            None => return Err(io::ErrorKind::NotFound.into()),
+        }
+        if let Some(None) = entries.get(path) {
+            return Err(io::ErrorKind::IsADirectory.into());
+        }
+
+        entries.insert(path.to_owned(), Some(contents.to_owned()));
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let parent = path.parent().ok_or(io::ErrorKind::InvalidInput)?;
+        match entries.get(parent) {
+            Some(None) => (),
+            Some(Some(_)) => return Err(io::ErrorKind::NotADirectory.into()),
+            None => return Err(io::ErrorKind::NotFound.into()),
+        }
+        if entries.contains_key(path) {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+
+        entries.insert(path.to_owned(), None);
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(from) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        let parent = to.parent().ok_or(io::ErrorKind::InvalidInput)?;
+        if !matches!(entries.get(parent), Some(None)) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        // move the entry and everything below it
+        let moved = entries
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in moved {
+            let contents = entries.remove(&path).expect("listed entry");
+            let rest = path.strip_prefix(from).expect("entry below `from`");
+            entries.insert(to.join(rest), contents);
+        }
+        Ok(())
+    }
+
+    fn trash(&self, path: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(path) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let trashed = entries
+            .extract_if(.., |entry, _| entry.starts_with(path))
+            .collect();
+        self.trashed
+            .lock()
+            .unwrap()
+            .push((path.to_owned(), trashed));
+        Ok(())
+    }
+
+    fn restore(&self, path: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.contains_key(path) {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+        let parent = path.parent().ok_or(io::ErrorKind::InvalidInput)?;
+        if !matches!(entries.get(parent), Some(None)) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+
+        let mut trashed = self.trashed.lock().unwrap();
+        let ix = trashed
+            .iter()
+            .rposition(|(trashed, _)| trashed == path)
+            .ok_or(io::ErrorKind::NotFound)?;
+        entries.extend(trashed.remove(ix).1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_fs() {
+        let fs = MemoryFs::new();
+        fs.create_dir_all("/doom/maps");
+        fs.write(Path::new("/doom/maps/MAP01.wad"), b"PWAD")
+            .unwrap();
+        fs.write(Path::new("/doom/MAPINFO"), b"map MAP01").unwrap();
+
+        assert_eq!(fs.read(Path::new("/doom/maps/MAP01.wad")).unwrap(), b"PWAD");
+        assert!(fs.write(Path::new("/heretic/MAPINFO"), b"").is_err());
+        assert_eq!(
+            fs.read_range(Path::new("/doom/MAPINFO"), 4, 100).unwrap(),
+            b"MAP01"
+        );
+
+        let walked = fs
+            .walk(Path::new("/doom"))
+            .map(|entry| entry.unwrap().path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            walked,
+            [
+                Path::new("/doom"),
+                Path::new("/doom/MAPINFO"),
+                Path::new("/doom/maps"),
+                Path::new("/doom/maps/MAP01.wad"),
+            ]
+        );
+    }
+}
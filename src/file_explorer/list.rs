@@ -1,108 +1,696 @@
 //! Floem virtual list implementation.
 
+use floem::reactive::{RwSignal, SignalWith};
 use floem::views::VirtualVector;
 
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::ops::Range;
+use std::path::Path;
 use std::rc::Rc;
+use std::time::SystemTime;
 
-use super::data::{Node, Tree, TreeIndex};
+use super::data::{Change, Node, Tree, TreeIndex};
 
 /// A virtual list for files
+///
+/// The view is a snapshot of the rows of a tree at one
+/// [revision](Tree::revision). The rows are listed the first time they are
+/// needed, and the nodes are read from the tree as they are shown, so the
+/// view must be rebuilt when the tree changes. [`TreeView::reusing`] builds
+/// the next snapshot from the rows of the last one.
+#[derive(Clone)]
 pub struct TreeView {
-    tree: Tree,
+    tree: RwSignal<Tree>,
+    revision: u64,
+    filter: Option<Rc<Filter>>,
+    sort: SortMode,
+    /// Whether every file is listed in a single, sorted list.
+    flat: bool,
+    hidden: Option<Rc<Hidden>>,
+    /// The rows, shared with the views of the same revision.
+    rows: Rc<OnceCell<Vec<Row>>>,
 }
 
 impl TreeView {
-    pub fn new(tree: Tree) -> TreeView {
-        TreeView { tree }
+    /// Creates a view of the tree in `tree`, tracking it.
+    pub fn new(tree: RwSignal<Tree>) -> TreeView {
+        TreeView {
+            revision: tree.with(Tree::revision),
+            tree,
+            filter: None,
+            sort: SortMode::default(),
+            flat: false,
+            hidden: None,
+            rows: Rc::default(),
+        }
+    }
+
+    /// Checks if the tree has not changed since the view was created.
+    pub fn is_current(&self) -> bool {
+        self.tree
+            .with_untracked(|tree| tree.revision() == self.revision)
+    }
+
+    /// Gets the row at `index`.
+    pub fn row(&self, index: usize) -> Option<NodeView> {
+        self.rows(index..index + 1).pop()
+    }
+
+    /// Finds the row of the node at `ix`, if it is visible.
+    pub fn position(&self, ix: TreeIndex) -> Option<usize> {
+        self.listed().iter().position(|row| row.ix == ix)
+    }
+
+    /// Finds the row of the node at `path`, if it is visible.
+    pub fn find(&self, path: &Path) -> Option<usize> {
+        let ix = self.tree.with_untracked(|tree| tree.find(path))?;
+        self.position(ix)
+    }
+
+    /// Gets the top of the viewport that brings the node at `ix` fully into
+    /// view, scrolling as little as possible. The viewport is `height` tall
+    /// and starts at `top`, and each row is `row_height` tall.
+    ///
+    /// Returns `None` if the node is not listed, or is already in view.
+    pub fn scroll_offset(
+        &self,
+        ix: TreeIndex,
+        top: f64,
+        height: f64,
+        row_height: f64,
+    ) -> Option<f64> {
+        let y = self.position(ix)? as f64 * row_height;
+        if y < top {
+            Some(y)
+        } else if y + row_height > top + height {
+            // rows taller than the viewport are scrolled to their top
+            Some((y + row_height - height).min(y))
+        } else {
+            None
+        }
+    }
+
+    /// Only shows the nodes matching `filter`, and their ancestors.
+    pub fn with_filter(self, filter: Option<Rc<Filter>>) -> TreeView {
+        TreeView { filter, ..self }
+    }
+
+    /// Lists siblings in the order given by `sort`.
+    pub fn with_sort(self, sort: SortMode) -> TreeView {
+        TreeView { sort, ..self }
+    }
+
+    /// Lists every file in the tree in a single, sorted list if `flat` is
+    /// `true`. Directories are not listed.
+    pub fn with_flat(self, flat: bool) -> TreeView {
+        TreeView { flat, ..self }
+    }
+
+    /// Leaves out the nodes `hidden` hides, and everything inside them.
+    pub fn with_hidden(self, hidden: Option<Rc<Hidden>>) -> TreeView {
+        TreeView { hidden, ..self }
+    }
+
+    /// Reuses the rows of `last`, a view of the same tree with the same
+    /// options, instead of listing them again.
+    ///
+    /// If the tree has changed since `last`, its rows are patched with the
+    /// [changes](Tree::changes_since) instead, as long as the tree still
+    /// remembers them and no nodes were created, removed or moved.
+    pub fn reusing(self, last: &TreeView) -> TreeView {
+        let same_options = self.tree == last.tree
+            && self.sort == last.sort
+            && self.flat == last.flat
+            && same_rc(&self.filter, &last.filter)
+            && same_rc(&self.hidden, &last.hidden);
+        if !same_options {
+            return self;
+        }
+        if self.revision == last.revision {
+            return TreeView {
+                rows: last.rows.clone(),
+                ..self
+            };
+        }
+
+        let Some(rows) = last.rows.get() else {
+            return self;
+        };
+        let rows = self.tree.with_untracked(|tree| {
+            let changes = tree.changes_since(last.revision)?;
+            self.patch(tree, rows.clone(), changes)
+        });
+        match rows {
+            Some(rows) => TreeView {
+                rows: Rc::new(OnceCell::from(rows)),
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    fn traverse<'a>(&'a self, tree: &'a Tree) -> TraverseTree<'a> {
+        TraverseTree::new(tree, self.filter.as_deref(), self.sort).hiding(self.hidden.as_deref())
+    }
+
+    /// The rows in `range`, with their nodes copied out of the tree.
+    fn rows(&self, range: Range<usize>) -> Vec<NodeView> {
+        let rows = self.listed();
+        let rows = &rows[range.start.min(rows.len())..range.end.min(rows.len())];
+        self.tree
+            .with_untracked(|tree| rows.iter().filter_map(|row| row.view(tree)).collect())
+    }
+
+    /// Every row, listed the first time they are needed.
+    fn listed(&self) -> &[Row] {
+        self.rows.get_or_init(|| {
+            self.tree.with_untracked(|tree| {
+                if self.flat {
+                    self.list_flat(tree)
+                } else {
+                    self.traverse(tree).collect()
+                }
+            })
+        })
+    }
+
+    /// Lists the files shown in flat mode.
+    fn list_flat(&self, tree: &Tree) -> Vec<Row> {
+        let mut files = tree
+            .iter()
+            .filter(|(ix, node)| {
+                !node.is_dir
+                    && self.filter.as_ref().is_none_or(|f| f.visible.contains(ix))
+                    && self
+                        .hidden
+                        .as_ref()
+                        .is_none_or(|hidden| !hidden.hides_ancestor(tree, *ix))
+            })
+            .collect::<Vec<_>>();
+        self.sort.sort(&mut files);
+        files
+            .into_iter()
+            .map(|(ix, _)| Row { ix, level: 0 })
+            .collect()
+    }
+
+    /// Applies `changes` to `rows`, the rows of an older revision of
+    /// `tree`.
+    ///
+    /// Returns `None` if the rows must be listed again.
+    fn patch(
+        &self,
+        tree: &Tree,
+        mut rows: Vec<Row>,
+        changes: impl Iterator<Item = Change>,
+    ) -> Option<Vec<Row>> {
+        for change in changes {
+            match change {
+                // the order of siblings only depends on metadata when
+                // sorting by it
+                Change::Node(_) => {
+                    if matches!(self.sort, SortMode::Modified | SortMode::Size) {
+                        return None;
+                    }
+                }
+                // every node is open when filtering, and flat lists have
+                // no open state
+                Change::Open(_) if self.flat || self.filter.is_some() => {}
+                Change::Open(ix) => {
+                    // the node is still hidden by a closed or hidden parent
+                    let Some(start) = rows.iter().position(|row| row.ix == ix) else {
+                        continue;
+                    };
+                    let level = rows[start].level;
+                    let end = rows[start + 1..]
+                        .iter()
+                        .position(|row| row.level <= level)
+                        .map_or(rows.len(), |len| start + 1 + len);
+                    let children = self.traverse(tree).starting_at(ix, level).skip(1);
+                    rows.splice(start + 1..end, children);
+                }
+            }
+        }
+        Some(rows)
+    }
+}
+
+/// Checks if two options point to the same value, or are both `None`.
+fn same_rc<T>(a: &Option<Rc<T>>, b: &Option<Rc<T>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+/// Nodes left out of the explorer, like build output.
+///
+/// Nodes are hidden if their file name, or their path relative to the root
+/// of the tree, matches one of the globs. The root is never hidden.
+#[derive(Clone, Debug, Default)]
+pub struct Hidden {
+    globs: globset::GlobSet,
+}
+
+impl Hidden {
+    /// Hides the nodes matching `globs`.
+    pub fn new(globs: globset::GlobSet) -> Hidden {
+        Hidden { globs }
+    }
+
+    /// Checks if `node` is hidden. Its ancestors are not checked.
+    pub fn hides(&self, tree: &Tree, node: &Node) -> bool {
+        let Ok(relative) = node.path.strip_prefix(tree.root().path()) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+
+        self.globs.is_match(relative)
+            || node
+                .path
+                .file_name()
+                .is_some_and(|name| self.globs.is_match(name))
+    }
+
+    /// Checks if the node at `ix`, or any of its ancestors, is hidden.
+    fn hides_ancestor(&self, tree: &Tree, ix: TreeIndex) -> bool {
+        let mut cur_ix = Some(ix);
+        while let Some(node) = cur_ix.and_then(|ix| tree.get(ix)) {
+            if self.hides(tree, node) {
+                return true;
+            }
+            cur_ix = node.parent;
+        }
+        false
+    }
+}
+
+/// How a list scrolls to a row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollBehavior {
+    /// Jumps straight to the row.
+    #[default]
+    Instant,
+    /// Glides to the row over a moment.
+    Smooth,
+}
+
+/// The order siblings are listed in. Directories always come first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortMode {
+    /// By file name.
+    #[default]
+    Name,
+    /// Most recently modified first.
+    Modified,
+    /// Largest first.
+    Size,
+    /// By file extension, then by name.
+    Type,
+}
+
+impl SortMode {
+    /// Every sort mode, in the order they are listed in menus.
+    pub const ALL: [SortMode; 4] = [
+        SortMode::Name,
+        SortMode::Modified,
+        SortMode::Size,
+        SortMode::Type,
+    ];
+
+    /// Finds the sort mode called `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<SortMode> {
+        SortMode::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The name of the sort mode shown in menus.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Name => "Name",
+            SortMode::Modified => "Modified",
+            SortMode::Size => "Size",
+            SortMode::Type => "Type",
+        }
+    }
+
+    /// Sorts sibling nodes.
+    fn sort(self, nodes: &mut [(TreeIndex, &Node)]) {
+        // lowercase each name once, rather than on every comparison
+        let mut keyed = nodes
+            .iter()
+            .map(|&(ix, node)| (lowercase_name(node), ix, node))
+            .collect::<Vec<_>>();
+        keyed.sort_by(|(a_name, _, a), (b_name, _, b)| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| self.compare(a, b))
+                .then_with(|| a_name.cmp(b_name))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        for (sorted, (_, ix, node)) in nodes.iter_mut().zip(keyed) {
+            *sorted = (ix, node);
+        }
+    }
+
+    fn compare(self, a: &Node, b: &Node) -> Ordering {
+        match self {
+            SortMode::Name => Ordering::Equal,
+            SortMode::Modified => {
+                let modified = |node: &Node| node.metadata().and_then(|m| m.modified);
+                modified(b).cmp(&modified(a))
+            }
+            SortMode::Size => {
+                let len = |node: &Node| node.metadata().map_or(0, |m| m.len);
+                len(b).cmp(&len(a))
+            }
+            SortMode::Type => {
+                let extension = |node: &Node| {
+                    node.path
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().to_lowercase())
+                };
+                extension(a).cmp(&extension(b))
+            }
+        }
     }
 }
 
+/// The file name of a node in lowercase, for comparing names ignoring case.
+fn lowercase_name(node: &Node) -> Option<String> {
+    node.path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+}
+
+/// The nodes shown when filtering a [`Tree`].
+///
+/// Nodes whose file name fuzzy matches the query are shown, along with all of
+/// their ancestors, regardless of whether the ancestors are open.
+#[derive(Debug)]
+pub struct Filter {
+    visible: HashSet<TreeIndex>,
+    match_count: usize,
+}
+
+impl Filter {
+    /// Creates a new `Filter` matching `query` against the nodes of `tree`.
+    pub fn new(tree: &Tree, query: &str) -> Filter {
+        let query = query.to_lowercase();
+        let mut visible = HashSet::new();
+        let mut match_count = 0;
+
+        // the root is always visible
+        visible.insert(TreeIndex::ROOT);
+
+        for (ix, node) in tree.iter() {
+            let Some(file_name) = node.path.file_name() else {
+                continue;
+            };
+            if ix == TreeIndex::ROOT || !fuzzy_match(&file_name.to_string_lossy(), &query) {
+                continue;
+            }
+
+            match_count += 1;
+
+            // mark the node and its ancestors as visible
+            let mut cur_ix = Some(ix);
+            while let Some(ix) = cur_ix {
+                if !visible.insert(ix) {
+                    break;
+                }
+                cur_ix = tree.get(ix).and_then(|n| n.parent);
+            }
+        }
+
+        Filter {
+            visible,
+            match_count,
+        }
+    }
+
+    /// The number of nodes that matched the query.
+    pub fn match_count(&self) -> usize {
+        self.match_count
+    }
+}
+
+/// Finds the `count` most recently modified files in `tree`, newest first.
+///
+/// Files whose modified time cannot be read are skipped.
+pub fn recent_files(tree: &Tree, count: usize) -> Vec<TreeIndex> {
+    let mut files = tree
+        .iter()
+        .filter(|(_, node)| !node.is_dir)
+        .filter_map(|(ix, node)| Some((node.metadata()?.modified?, ix)))
+        .collect::<Vec<_>>();
+
+    // only the newest files need to be sorted
+    let newest_first = |a: &(SystemTime, TreeIndex), b: &(SystemTime, TreeIndex)| b.0.cmp(&a.0);
+    if files.len() > count {
+        files.select_nth_unstable_by(count, newest_first);
+        files.truncate(count);
+    }
+    files.sort_by(newest_first);
+
+    files.into_iter().map(|(_, ix)| ix).collect()
+}
+
+/// Finds the files in `tree` matching `pattern`, a glob like `src/**/*.rs`,
+/// sorted by path.
+///
+/// Like [`Hidden`], a file matches if its path relative to the root of the
+/// tree, or its file name, matches the glob.
+pub fn find_by_pattern(tree: &Tree, pattern: &str) -> Result<Vec<TreeIndex>, globset::Error> {
+    // `*` stops at separators, so `src/*.rs` does not match `src/bin/main.rs`
+    let glob = globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+    let root = tree.root().path();
+
+    let mut files = tree
+        .iter()
+        .filter(|(_, node)| !node.is_dir)
+        .filter(|(_, node)| {
+            node.path
+                .strip_prefix(root)
+                .is_ok_and(|relative| glob.is_match(relative))
+                || node
+                    .path
+                    .file_name()
+                    .is_some_and(|name| glob.is_match(name))
+        })
+        .map(|(ix, node)| (node.path.as_path(), ix))
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| a.0.cmp(b.0));
+
+    Ok(files.into_iter().map(|(_, ix)| ix).collect())
+}
+
+/// Checks if all the characters of `query` appear in `s` in order, ignoring
+/// case. `query` must already be lowercase.
+fn fuzzy_match(s: &str, query: &str) -> bool {
+    let mut chars = s.chars().flat_map(char::to_lowercase);
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
 /// A single virtual node.
 pub struct NodeView {
+    /// The index of the node in the tree.
+    pub ix: TreeIndex,
     /// The actual node.
-    pub node: Rc<Node>,
+    pub node: Node,
     /// The level of the node.
     pub level: usize,
 }
 
 impl NodeView {
-    /// The reduced filename of the node.
-    pub fn file_name(&self) -> &str {
-        // TODO maybe not unwrap this
+    /// The reduced filename of the node. A root without a filename, like
+    /// `/`, is shown whole.
+    pub fn file_name(&self) -> Cow<'_, str> {
+        let path = &self.node.path;
+        match path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => Cow::Owned(path.display().to_string()),
+        }
+    }
+
+    /// The size of the node for the details columns. Empty for directories.
+    pub fn size_text(&self) -> String {
+        match self.node.metadata() {
+            Some(metadata) if !self.node.is_dir => format_size(metadata.len),
+            _ => String::new(),
+        }
+    }
+
+    /// The last-modified time of the node for the details columns.
+    pub fn modified_text(&self) -> String {
         self.node
-            .path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .expect("path")
+            .metadata()
+            .and_then(|metadata| metadata.modified)
+            .map(|time| {
+                chrono::DateTime::<chrono::Local>::from(time)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default()
     }
 }
 
+/// Formats a file size in bytes for display.
+fn format_size(len: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if len < 1024 {
+        return format!("{} B", len);
+    }
+    let mut size = len as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
 impl VirtualVector<NodeView> for TreeView {
     fn total_len(&self) -> usize {
-        self.tree.root().children_open_count + 1
+        self.listed().len()
     }
 
     fn slice(&mut self, range: Range<usize>) -> impl Iterator<Item = NodeView> {
-        TraverseTree::new(&self.tree)
-            .skip(range.start)
-            .take(range.len())
+        self.rows(range).into_iter()
+    }
+}
+
+/// A row of a [`TreeView`], before its node is copied out of the tree.
+#[derive(Clone, Copy)]
+struct Row {
+    ix: TreeIndex,
+    level: usize,
+}
+
+impl Row {
+    fn view(&self, tree: &Tree) -> Option<NodeView> {
+        Some(NodeView {
+            ix: self.ix,
+            node: tree.get(self.ix)?.clone(),
+            level: self.level,
+        })
     }
 }
 
 /// Iterates over all the nodes in a [`Tree`], and their children.
+///
+/// If a [`Filter`] is given, only visible nodes are iterated over, and every
+/// visible node is treated as open. Siblings are ordered by a [`SortMode`].
 struct TraverseTree<'a> {
     tree: &'a Tree,
+    filter: Option<&'a Filter>,
+    sort: SortMode,
+    hidden: Option<&'a Hidden>,
     stack: Vec<TraverseEl>,
+    started: bool,
+    /// The node iterated over first, and its level.
+    start: (TreeIndex, usize),
 }
 
 impl<'a> TraverseTree<'a> {
-    pub fn new(tree: &'a Tree) -> TraverseTree<'a> {
+    pub fn new(tree: &'a Tree, filter: Option<&'a Filter>, sort: SortMode) -> TraverseTree<'a> {
         TraverseTree {
-            stack: vec![TraverseEl {
-                ix: TreeIndex::ROOT,
-                child_ix: 0,
-            }],
+            stack: Vec::new(),
+            started: false,
             tree,
+            filter,
+            sort,
+            hidden: None,
+            start: (TreeIndex::ROOT, 0),
         }
     }
+
+    /// Only iterates over the node at `ix` and its children, treating the
+    /// node as `level` levels deep.
+    pub fn starting_at(self, ix: TreeIndex, level: usize) -> TraverseTree<'a> {
+        TraverseTree {
+            start: (ix, level),
+            ..self
+        }
+    }
+
+    /// Skips the nodes `hidden` hides, and everything inside them.
+    pub fn hiding(self, hidden: Option<&'a Hidden>) -> TraverseTree<'a> {
+        TraverseTree { hidden, ..self }
+    }
+
+    /// Starts iterating over the children of the node at `ix`.
+    fn push(&mut self, ix: TreeIndex) {
+        let tree = self.tree;
+        let mut children = tree
+            .children(ix)
+            .filter(|(_, node)| self.hidden.is_none_or(|hidden| !hidden.hides(tree, node)))
+            .collect::<Vec<_>>();
+        self.sort.sort(&mut children);
+
+        self.stack.push(TraverseEl {
+            children: children.into_iter().map(|(ix, _)| ix).collect(),
+            child_ix: 0,
+        });
+    }
+
+    /// Checks if the children of a node should be iterated over.
+    fn is_open(&self, node: &Node) -> bool {
+        self.filter.is_some() || node.is_open
+    }
 }
 
 struct TraverseEl {
-    ix: TreeIndex,
+    /// The sorted children of the node.
+    children: Vec<TreeIndex>,
     child_ix: usize,
 }
 
 impl<'a> Iterator for TraverseTree<'a> {
-    type Item = NodeView;
+    type Item = Row;
 
     fn next(&mut self) -> Option<Self::Item> {
+        // yield the starting node first
+        if !self.started {
+            self.started = true;
+            let (ix, level) = self.start;
+            if self.is_open(self.tree.get(ix)?) {
+                self.push(ix);
+            }
+            return Some(Row { ix, level });
+        }
+
         loop {
             // check tos
             if let Some(tos) = self.stack.last_mut() {
                 // continue where we left off
-                let next = self
-                    .tree
-                    .get(tos.ix)
-                    .expect("valid node")
-                    .children
-                    .values()
-                    .skip(tos.child_ix)
-                    .copied()
-                    .next();
+                let next = tos.children.get(tos.child_ix).copied();
 
                 if let Some(next_ix) = next {
                     tos.child_ix += 1;
-                    let out = NodeView {
-                        node: self.tree.get(next_ix).expect("valid node").clone(),
-                        level: self.stack.len() - 1,
+
+                    // skip nodes hidden by the filter
+                    if self.filter.is_some_and(|f| !f.visible.contains(&next_ix)) {
+                        continue;
+                    }
+
+                    let out = Row {
+                        ix: next_ix,
+                        level: self.start.1 + self.stack.len(),
                     };
-                    if out.node.is_open {
+                    if self.is_open(self.tree.get(next_ix).expect("valid node")) {
                         // iterate over children
-                        self.stack.push(TraverseEl {
-                            ix: next_ix,
-                            child_ix: 0,
-                        });
+                        self.push(next_ix);
                     }
                     return Some(out);
                 } else {
@@ -119,6 +707,7 @@ impl<'a> Iterator for TraverseTree<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use floem::reactive::{create_rw_signal, SignalUpdate};
     use std::path::PathBuf;
 
     #[test]
@@ -145,8 +734,8 @@ mod tests {
         tree.create(Node::new("/var/games/spelunky"));
         tree.create(Node::new("/var/games/minesweeper"));
 
-        let mut out = TraverseTree::new(&tree)
-            .map(|s| s.node.path().to_owned())
+        let mut out = TraverseTree::new(&tree, None, SortMode::Name)
+            .map(|s| tree.get(s.ix).unwrap().path().to_owned())
             .collect::<Vec<_>>();
         out.sort();
 
@@ -162,4 +751,294 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_filtered_traversal() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/opt/hidden"));
+        tree.create(Node::new("/var/opt/hidden2"));
+        tree.create(Node::new("/var/games/battleblock"));
+        tree.create(Node::new("/var/games/spelunky"));
+
+        let filter = Filter::new(&tree, "HDN");
+        assert_eq!(filter.match_count(), 2);
+
+        let mut out = TraverseTree::new(&tree, Some(&filter), SortMode::Name)
+            .map(|s| tree.get(s.ix).unwrap().path().to_owned())
+            .collect::<Vec<_>>();
+        out.sort();
+
+        assert_eq!(out.len(), filter.visible.len());
+        assert_eq!(
+            out,
+            vec![
+                PathBuf::from("/var"),
+                PathBuf::from("/var/opt"),
+                PathBuf::from("/var/opt/hidden"),
+                PathBuf::from("/var/opt/hidden2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relative_root() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new(".")
+        });
+        tree.create(Node::new("./doom2.wad"));
+
+        let names = TraverseTree::new(&tree, None, SortMode::Name)
+            .map(|row| row.view(&tree).unwrap().file_name().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [".", "doom2.wad"]);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(14_000_000), "13.4 MiB");
+    }
+
+    #[test]
+    fn test_sorted_traversal() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/Zdoom.wad"));
+        tree.create(Node::new("/var/doom2.wad"));
+        tree.create(Node::new("/var/zdaemon.cfg"));
+        tree.create(Node {
+            is_dir: true,
+            ..Node::new("/var/saves")
+        });
+
+        let names = |sort| {
+            TraverseTree::new(&tree, None, sort)
+                .skip(1)
+                .map(|row| row.view(&tree).unwrap().file_name().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(SortMode::Name),
+            ["saves", "doom2.wad", "zdaemon.cfg", "Zdoom.wad"]
+        );
+        assert_eq!(
+            names(SortMode::Type),
+            ["saves", "zdaemon.cfg", "doom2.wad", "Zdoom.wad"]
+        );
+    }
+
+    #[test]
+    fn test_flat_view() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/heretic.wad"));
+        tree.create(Node::new("/var/autoexec.cfg"));
+
+        let mut view = TreeView::new(create_rw_signal(tree.clone())).with_flat(true);
+        let names = view
+            .slice(0..view.total_len())
+            .map(|row| row.file_name().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["autoexec.cfg", "doom2.wad", "heretic.wad"]);
+
+        let ix = tree.find("/var/games/heretic.wad").unwrap();
+        assert_eq!(view.position(ix), Some(2));
+        assert_eq!(view.slice(2..5).count(), 1);
+    }
+
+    #[test]
+    fn test_scroll_offset() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        for map in 1..=9 {
+            tree.create(Node::new(format!("/var/MAP0{}.wad", map)));
+        }
+        let tree = create_rw_signal(tree);
+        let view = TreeView::new(tree);
+        let ix = |path| tree.with_untracked(|tree| tree.find(path).unwrap());
+
+        // rows 3..6 are in view
+        assert_eq!(
+            view.scroll_offset(ix("/var/MAP01.wad"), 30.0, 30.0, 10.0),
+            Some(10.0)
+        );
+        assert_eq!(
+            view.scroll_offset(ix("/var/MAP04.wad"), 30.0, 30.0, 10.0),
+            None
+        );
+        assert_eq!(
+            view.scroll_offset(ix("/var/MAP08.wad"), 30.0, 30.0, 10.0),
+            Some(60.0)
+        );
+        // taller than the viewport
+        assert_eq!(
+            view.scroll_offset(ix("/var/MAP08.wad"), 30.0, 5.0, 10.0),
+            Some(80.0)
+        );
+    }
+
+    #[test]
+    fn test_recent_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new(&dir)
+        });
+        let now = std::time::SystemTime::now();
+        for (i, name) in ["MAP01.wad", "MAP02.wad", "MAP03.wad"].iter().enumerate() {
+            let path = dir.join(name);
+            let file = std::fs::File::create(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(60 * i as u64))
+                .unwrap();
+            tree.create(Node::new(path));
+        }
+
+        let recent = recent_files(&tree, 2)
+            .into_iter()
+            .map(|ix| tree.get(ix).unwrap().path().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(recent, [dir.join("MAP01.wad"), dir.join("MAP02.wad")]);
+    }
+
+    #[test]
+    fn test_find_by_pattern() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/src/main.rs"));
+        tree.create(Node::new("/var/src/bin/test_maps.rs"));
+        tree.create(Node::new("/var/tests/test_wad.txt"));
+
+        let find = |pattern| {
+            find_by_pattern(&tree, pattern)
+                .unwrap()
+                .into_iter()
+                .map(|ix| tree.get(ix).unwrap().path().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            find("src/**/*.rs"),
+            [
+                PathBuf::from("/var/src/bin/test_maps.rs"),
+                PathBuf::from("/var/src/main.rs")
+            ]
+        );
+        assert_eq!(find("src/*.rs"), [PathBuf::from("/var/src/main.rs")]);
+        assert_eq!(
+            find("*test*"),
+            [
+                PathBuf::from("/var/src/bin/test_maps.rs"),
+                PathBuf::from("/var/tests/test_wad.txt")
+            ]
+        );
+        assert!(find_by_pattern(&tree, "src/[").is_err());
+    }
+
+    #[test]
+    fn test_hidden() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var/target")
+        });
+        tree.create(Node::new("/var/target/mobius"));
+        tree.create(Node::new("/var/games/doom2.wad"));
+        tree.create(Node::new("/var/games/doom2.wad.bak"));
+
+        let mut globs = globset::GlobSetBuilder::new();
+        globs.add(globset::Glob::new("target").unwrap());
+        globs.add(globset::Glob::new("*.bak").unwrap());
+        let hidden = Rc::new(Hidden::new(globs.build().unwrap()));
+
+        let mut view =
+            TreeView::new(create_rw_signal(tree.clone())).with_hidden(Some(hidden.clone()));
+        assert_eq!(view.total_len(), 2);
+        let rows = view
+            .slice(0..2)
+            .map(|row| row.node.path().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(rows, [PathBuf::from("/var"), PathBuf::from("/var/games")]);
+
+        let view = TreeView::new(create_rw_signal(tree))
+            .with_hidden(Some(hidden))
+            .with_flat(true);
+        assert_eq!(view.total_len(), 1);
+        assert_eq!(view.row(0).unwrap().file_name(), "doom2.wad");
+    }
+
+    #[test]
+    fn test_reusing() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom/doom2.wad"));
+        tree.create(Node::new("/var/games/heretic.wad"));
+        tree.create(Node::new("/var/opt/tnt.wad"));
+        let games = tree.find("/var/games").unwrap();
+        let doom = tree.find("/var/games/doom").unwrap();
+        let tree = create_rw_signal(tree);
+
+        let paths = |mut view: TreeView| {
+            view.slice(0..view.total_len())
+                .map(|row| row.node.path)
+                .collect::<Vec<_>>()
+        };
+        let mut last = TreeView::new(tree);
+        last.total_len();
+
+        // opening and closing nodes patches the rows
+        for (ix, is_open) in [(games, true), (doom, true), (games, false), (games, true)] {
+            tree.update(|tree| tree.set_open(ix, is_open));
+            let view = TreeView::new(tree).reusing(&last);
+            assert!(view.rows.get().is_some());
+            assert_eq!(paths(view.clone()), paths(TreeView::new(tree)));
+            last = view;
+        }
+
+        // views of the same revision share their rows
+        let view = TreeView::new(tree).reusing(&last);
+        assert!(Rc::ptr_eq(&view.rows, &last.rows));
+
+        // other options list the rows again
+        let view = TreeView::new(tree).with_flat(true).reusing(&last);
+        assert!(view.rows.get().is_none());
+
+        tree.update(|tree| {
+            tree.create(Node::new("/var/games/doom/tnt.wad"));
+        });
+        let view = TreeView::new(tree).reusing(&last);
+        assert!(view.rows.get().is_none());
+        assert_eq!(view.total_len(), 7);
+    }
 }
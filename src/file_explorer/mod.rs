@@ -1,5 +1,14 @@
 //! Sidebar file explorer.
 
+pub mod archive;
+pub mod badges;
 pub mod data;
+pub mod diff;
+pub mod favorites;
+pub mod fs;
 pub mod list;
+pub mod ops;
+pub mod preview;
+pub mod scan;
+pub mod state;
 pub mod view;
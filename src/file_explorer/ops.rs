@@ -0,0 +1,846 @@
+//! File operations performed from the explorer.
+//!
+//! Operations are applied on disk and recorded in an [`OpLog`] so they can be
+//! undone and redone later. Each returns a [`FileOp`] describing the change,
+//! which is then applied to every [`Tree`] showing the affected paths.
+//!
+//! Deleting or moving a [protected](Protection) path fails until it is
+//! confirmed with [`OpLog::confirm`].
+//!
+//! Files are read, written and moved to the trash through an [`FsProvider`].
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use super::data::Tree;
+use super::fs::{walk_links, FsProvider, LocalFs};
+use super::scan;
+
+/// The most operations an [`OpLog`] remembers.
+const MAX_OPS: usize = 100;
+/// The most entries looked through for protected paths below a directory.
+/// Larger directories are treated as protected.
+const MAX_PROTECTION_WALK: usize = 10_000;
+
+/// A file operation.
+#[derive(Clone, Debug)]
+pub enum FileOp {
+    /// A file or directory was renamed.
+    Rename { from: PathBuf, to: PathBuf },
+    /// A file or directory was copied.
+    Copy { from: PathBuf, to: PathBuf },
+    /// A new file was created.
+    Create { path: PathBuf },
+    /// A file or directory was moved to the trash.
+    Delete { path: PathBuf },
+    /// A file or directory was restored from the trash.
+    Restore { path: PathBuf },
+}
+
+impl FileOp {
+    /// Applies the operation to `tree`.
+    ///
+    /// Paths that cannot be represented in the tree are ignored, so an
+    /// operation can be applied to every open tree.
+    pub fn apply(&self, tree: &mut Tree) {
+        self.apply_with(&LocalFs::shared(), tree)
+    }
+
+    /// Like [`FileOp::apply`], but reads new nodes from `fs`.
+    pub fn apply_with(&self, fs: &Arc<dyn FsProvider>, tree: &mut Tree) {
+        match self {
+            FileOp::Rename { from, to } => match tree.find(from) {
+                Some(ix) => {
+                    // moved out of the tree
+                    if tree.rename(ix, to).is_none() {
+                        tree.remove(ix);
+                    }
+                }
+                // moved into the tree
+                None => scan::load_with(fs, tree, to),
+            },
+            FileOp::Copy { to: path, .. } | FileOp::Create { path } | FileOp::Restore { path } => {
+                scan::load_with(fs, tree, path)
+            }
+            FileOp::Delete { path } => {
+                if let Some(ix) = tree.find(path) {
+                    tree.remove(ix);
+                }
+            }
+        }
+    }
+}
+
+/// Paths that cannot be deleted or moved without a typed confirmation.
+///
+/// `.git` directories are always protected. So is every directory containing
+/// a protected path, since deleting or moving it takes the path with it.
+#[derive(Clone, Debug, Default)]
+pub struct Protection {
+    /// The root the globs are matched relative to.
+    root: PathBuf,
+    paths: Vec<PathBuf>,
+    globs: globset::GlobSet,
+}
+
+impl Protection {
+    /// Protects `paths`, and every path below `root` matching one of `globs`
+    /// relative to `root`, like `maps/*.wad`.
+    pub fn new(
+        root: impl Into<PathBuf>,
+        paths: impl IntoIterator<Item = PathBuf>,
+        globs: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Protection, globset::Error> {
+        let mut builder = globset::GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(globset::Glob::new(glob.as_ref())?);
+        }
+
+        Ok(Protection {
+            root: root.into(),
+            paths: paths.into_iter().collect(),
+            globs: builder.build()?,
+        })
+    }
+
+    /// Checks if `path` is protected, or contains a protected path in `fs`.
+    ///
+    /// Symlinks below `path` are not followed. Directories with more than
+    /// [`MAX_PROTECTION_WALK`] entries are protected without looking through
+    /// all of them, so checking stays quick.
+    pub fn is_protected(&self, fs: &dyn FsProvider, path: &Path) -> bool {
+        if self
+            .paths
+            .iter()
+            .any(|protected| protected.starts_with(path))
+            || self.matches(path)
+        {
+            return true;
+        }
+
+        let mut entries = walk_links(fs, path).skip(1).filter_map(Result::ok);
+        entries
+            .by_ref()
+            .take(MAX_PROTECTION_WALK)
+            .any(|entry| self.matches(&entry.path))
+            || entries.next().is_some()
+    }
+
+    /// Checks if `path` is a `.git` entry or matches one of the globs.
+    fn matches(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|name| name == ".git")
+            || path
+                .strip_prefix(&self.root)
+                .is_ok_and(|relative| self.globs.is_match(relative))
+    }
+}
+
+/// A log of file operations that can be undone and redone.
+///
+/// Only the last [`MAX_OPS`] operations are kept. An operation is not undone
+/// or redone if its files changed on disk since.
+#[derive(Clone, Debug)]
+pub struct OpLog {
+    fs: Arc<dyn FsProvider>,
+    ops: VecDeque<Entry>,
+    /// Undone operations, most recent last.
+    undone: Vec<Entry>,
+    protection: Protection,
+    /// The protected path allowed for the next operation.
+    confirmed: Option<PathBuf>,
+}
+
+impl OpLog {
+    /// Creates a new, empty `OpLog` on the local disk.
+    pub fn new() -> OpLog {
+        OpLog::default()
+    }
+
+    /// Performs operations in `fs` instead of on the local disk.
+    pub fn with_fs(self, fs: Arc<dyn FsProvider>) -> OpLog {
+        OpLog { fs, ..self }
+    }
+
+    /// Guards the paths in `protection` from being deleted or moved.
+    pub fn with_protection(self, protection: Protection) -> OpLog {
+        OpLog { protection, ..self }
+    }
+
+    /// Replaces the protected paths.
+    pub fn set_protection(&mut self, protection: Protection) {
+        self.protection = protection;
+    }
+
+    /// Allows the next operation to delete or move the protected `path`.
+    ///
+    /// `typed` must be the file name of `path`, as typed by the user.
+    pub fn confirm(&mut self, path: &Path, typed: &str) -> Result<(), Error> {
+        if path.file_name().is_none_or(|name| name != typed) {
+            return Err(Error::Protected(path.to_owned()));
+        }
+        self.confirmed = Some(path.to_owned());
+        Ok(())
+    }
+
+    /// Checks if there is an operation to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.ops.is_empty()
+    }
+
+    /// Checks if there is an operation to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Renames a file or directory.
+    ///
+    /// Fails if `to` already exists.
+    pub fn rename(
+        &mut self,
+        from: impl Into<PathBuf>,
+        to: impl Into<PathBuf>,
+    ) -> Result<FileOp, Error> {
+        let (from, to) = (from.into(), to.into());
+
+        self.check_protected(&from)?;
+        rename(&*self.fs, &from, &to)?;
+        Ok(self.push(FileOp::Rename { from, to }))
+    }
+
+    /// Copies a file or directory.
+    ///
+    /// Fails if `to` already exists.
+    pub fn copy(
+        &mut self,
+        from: impl Into<PathBuf>,
+        to: impl Into<PathBuf>,
+    ) -> Result<FileOp, Error> {
+        let (from, to) = (from.into(), to.into());
+
+        if self.fs.entry(&to).is_ok() {
+            return Err(Error::AlreadyExists(to));
+        }
+        copy(&*self.fs, &from, &to)?;
+        Ok(self.push(FileOp::Copy { from, to }))
+    }
+
+    /// Creates a new file at `path` holding `contents`.
+    ///
+    /// Fails if `path` already exists.
+    pub fn create(&mut self, path: impl Into<PathBuf>, contents: &[u8]) -> Result<FileOp, Error> {
+        let path = path.into();
+
+        if self.fs.entry(&path).is_ok() {
+            return Err(Error::AlreadyExists(path));
+        }
+        self.fs.write(&path, contents)?;
+        Ok(self.push(FileOp::Create { path }))
+    }
+
+    /// Moves a file or directory to the trash.
+    pub fn delete(&mut self, path: impl Into<PathBuf>) -> Result<FileOp, Error> {
+        let path = path.into();
+
+        self.check_protected(&path)?;
+        self.fs.trash(&path)?;
+        Ok(self.push(FileOp::Delete { path }))
+    }
+
+    /// Undoes the last file operation.
+    ///
+    /// Returns the reverse operation to apply to the trees, or `None` if there
+    /// was nothing to undo. If undoing fails, the operation is kept in the
+    /// log.
+    pub fn undo(&mut self) -> Result<Option<FileOp>, Error> {
+        let Some(entry) = self.ops.pop_back() else {
+            return Ok(None);
+        };
+
+        let result = entry.check(&*self.fs).and_then(|_| match &entry.op {
+            FileOp::Rename { from, to } => rename(&*self.fs, to, from).map(|_| FileOp::Rename {
+                from: to.clone(),
+                to: from.clone(),
+            }),
+            FileOp::Copy { to: path, .. } | FileOp::Create { path } => self
+                .fs
+                .trash(path)
+                .map(|_| FileOp::Delete { path: path.clone() })
+                .map_err(Error::from),
+            FileOp::Delete { path } => self
+                .fs
+                .restore(path)
+                .map(|_| FileOp::Restore { path: path.clone() })
+                .map_err(Error::from),
+            FileOp::Restore { .. } => unreachable!("restores are never logged"),
+        });
+
+        match result {
+            Ok(reverse) => {
+                self.undone.push(Entry::new(&*self.fs, entry.op, &reverse));
+                Ok(Some(reverse))
+            }
+            Err(err) => {
+                self.ops.push_back(entry);
+                Err(err)
+            }
+        }
+    }
+
+    /// Redoes the last undone file operation.
+    ///
+    /// Returns the operation to apply to the trees, or `None` if there was
+    /// nothing to redo. If redoing fails, the operation can still be redone.
+    pub fn redo(&mut self) -> Result<Option<FileOp>, Error> {
+        let Some(entry) = self.undone.pop() else {
+            return Ok(None);
+        };
+
+        let result = entry.check(&*self.fs).and_then(|_| match &entry.op {
+            FileOp::Rename { from, to } => rename(&*self.fs, from, to).map(|_| entry.op.clone()),
+            // undoing trashed the new file, so take it back out
+            FileOp::Copy { to: path, .. } | FileOp::Create { path } => self
+                .fs
+                .restore(path)
+                .map(|_| FileOp::Restore { path: path.clone() })
+                .map_err(Error::from),
+            FileOp::Delete { path } => self
+                .fs
+                .trash(path)
+                .map(|_| entry.op.clone())
+                .map_err(Error::from),
+            FileOp::Restore { .. } => unreachable!("restores are never logged"),
+        });
+
+        match result {
+            Ok(op) => {
+                self.record(Entry::new(&*self.fs, entry.op, &op));
+                Ok(Some(op))
+            }
+            Err(err) => {
+                self.undone.push(entry);
+                Err(err)
+            }
+        }
+    }
+
+    /// Fails if `path` is protected and has not been confirmed. A
+    /// confirmation only lasts for one operation.
+    fn check_protected(&mut self, path: &Path) -> Result<(), Error> {
+        let confirmed = self.confirmed.take();
+        if self.protection.is_protected(&*self.fs, path) && confirmed.as_deref() != Some(path) {
+            return Err(Error::Protected(path.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Logs a new operation, which cannot be redone past.
+    fn push(&mut self, op: FileOp) -> FileOp {
+        self.undone.clear();
+        self.record(Entry::new(&*self.fs, op.clone(), &op));
+        op
+    }
+
+    fn record(&mut self, entry: Entry) {
+        if self.ops.len() >= MAX_OPS {
+            self.ops.pop_front();
+        }
+        self.ops.push_back(entry);
+    }
+}
+
+impl Default for OpLog {
+    fn default() -> OpLog {
+        OpLog {
+            fs: LocalFs::shared(),
+            ops: VecDeque::new(),
+            undone: Vec::new(),
+            protection: Protection::default(),
+            confirmed: None,
+        }
+    }
+}
+
+/// A logged operation, along with the state of its result on disk.
+#[derive(Clone, Debug)]
+struct Entry {
+    op: FileOp,
+    /// The path the last change left behind.
+    path: PathBuf,
+    /// The stamp of `path` right after the change, or `None` if it did not
+    /// exist.
+    stamp: Option<Stamp>,
+}
+
+impl Entry {
+    /// Logs `op`, after `done` was applied in `fs`.
+    fn new(fs: &dyn FsProvider, op: FileOp, done: &FileOp) -> Entry {
+        let path = match done {
+            FileOp::Rename { to: path, .. }
+            | FileOp::Copy { to: path, .. }
+            | FileOp::Create { path }
+            | FileOp::Delete { path }
+            | FileOp::Restore { path } => path.clone(),
+        };
+        let stamp = Stamp::read(fs, &path);
+
+        Entry { op, path, stamp }
+    }
+
+    /// Fails if the path changed in `fs` since the entry was logged.
+    fn check(&self, fs: &dyn FsProvider) -> Result<(), Error> {
+        if Stamp::read(fs, &self.path) != self.stamp {
+            return Err(Error::Changed(self.path.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// The size and modified time of a file, used to tell if it changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Stamp {
+    /// Reads the stamp of `path`, or `None` if it does not exist.
+    fn read(fs: &dyn FsProvider, path: &Path) -> Option<Stamp> {
+        fs.entry(path).ok()?;
+        // broken symlinks exist, but have no metadata
+        let metadata = fs.metadata(path).ok();
+        Some(Stamp {
+            len: metadata.map_or(0, |metadata| metadata.len),
+            modified: metadata.and_then(|metadata| metadata.modified),
+        })
+    }
+}
+
+/// Finds a free path to copy a file or directory to `to`.
+///
+/// If `to` already exists, a suffix is added to its name, like
+/// `doom2 (copy).wad` or `doom2 (copy 2).wad`.
+pub fn copy_path(to: &Path) -> PathBuf {
+    copy_path_with(&LocalFs, to)
+}
+
+/// Like [`copy_path`], but looks for a free path in `fs`.
+pub fn copy_path_with(fs: &dyn FsProvider, to: &Path) -> PathBuf {
+    free_copy_path(to, |path| fs.entry(path).is_ok())
+}
+
+fn free_copy_path(to: &Path, exists: impl Fn(&Path) -> bool) -> PathBuf {
+    if !exists(to) {
+        return to.to_owned();
+    }
+
+    let stem = to
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = to
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 {
+                " (copy)".to_owned()
+            } else {
+                format!(" (copy {})", n)
+            };
+            to.with_file_name(format!("{}{}{}", stem, suffix, extension))
+        })
+        .find(|path| !exists(path))
+        .expect("a free path")
+}
+
+/// Lets the owner of the file or directory at `path` write to it.
+pub fn make_writable(path: &Path) -> Result<(), Error> {
+    LocalFs.set_writable(path)?;
+    Ok(())
+}
+
+/// Fills in a template like `{rel}:1` with the parts of `path`.
+///
+/// `{path}` is the absolute path, `{rel}` the path relative to `root`,
+/// `{name}` the file name and `{dir}` the directory relative to `root`. Other
+/// text, including unknown placeholders, is kept as is.
+pub fn format_path(template: &str, root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    let parts = [
+        ("{path}", path.display().to_string()),
+        ("{rel}", rel.display().to_string()),
+        (
+            "{name}",
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        ),
+        (
+            "{dir}",
+            rel.parent()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+        ),
+    ];
+
+    let mut formatted = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match parts.iter().find(|(key, _)| rest.starts_with(key)) {
+            Some((key, value)) => {
+                formatted.push_str(value);
+                rest = &rest[key.len()..];
+            }
+            None => {
+                formatted.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    formatted.push_str(rest);
+    formatted
+}
+
+/// Checks that `name` can be used as the name of a file or directory.
+pub fn check_name(name: &str) -> Result<(), Error> {
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains('\0')
+        || name.chars().any(std::path::is_separator)
+    {
+        return Err(Error::InvalidName(name.to_owned()));
+    }
+    Ok(())
+}
+
+fn rename(fs: &dyn FsProvider, from: &Path, to: &Path) -> Result<(), Error> {
+    if fs.entry(to).is_ok() {
+        return Err(Error::AlreadyExists(to.to_owned()));
+    }
+    fs.rename(from, to)?;
+    Ok(())
+}
+
+/// Copies a file or directory and all of its contents.
+///
+/// Symlinks are copied as links to the same target, not followed.
+fn copy(fs: &dyn FsProvider, from: &Path, to: &Path) -> Result<(), Error> {
+    for entry in walk_links(fs, from) {
+        let entry = entry?;
+        let dest = to.join(entry.path.strip_prefix(from).expect("entry in walked dir"));
+
+        if let Some(target) = &entry.link_target {
+            fs.symlink(target, &dest)?;
+        } else if entry.is_dir {
+            fs.create_dir(&dest)?;
+        } else {
+            fs.copy_file(&entry.path, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// An error that can occur during a file operation.
+#[derive(Debug)]
+pub enum Error {
+    /// The destination path already exists.
+    AlreadyExists(PathBuf),
+    /// The path is protected, and the operation was not confirmed.
+    Protected(PathBuf),
+    /// The name is not a valid file name.
+    InvalidName(String),
+    /// The path changed on disk since the operation being undone or redone.
+    Changed(PathBuf),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AlreadyExists(path) => write!(f, "{} already exists", path.display()),
+            Error::Protected(path) => write!(f, "{} is protected", path.display()),
+            Error::InvalidName(name) => write!(f, "{:?} is not a valid file name", name),
+            Error::Changed(path) => write!(f, "{} has changed since", path.display()),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_explorer::data::Node;
+    use crate::file_explorer::fs::MemoryFs;
+
+    #[test]
+    fn test_apply() {
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/var")
+        });
+        tree.create(Node::new("/var/games/doom2.wad"));
+        tree.create(Node::new("/var/opt"));
+
+        FileOp::Rename {
+            from: "/var/games".into(),
+            to: "/var/wads".into(),
+        }
+        .apply(&mut tree);
+        assert!(tree.find("/var/games").is_none());
+        assert!(tree.find("/var/wads/doom2.wad").is_some());
+
+        // moving out of the tree removes the node
+        FileOp::Rename {
+            from: "/var/wads".into(),
+            to: "/home/wads".into(),
+        }
+        .apply(&mut tree);
+        assert!(tree.find("/var/wads").is_none());
+
+        FileOp::Delete {
+            path: "/var/opt".into(),
+        }
+        .apply(&mut tree);
+        assert!(tree.find("/var/opt").is_none());
+        assert_eq!(tree.root().children_open_count, 0);
+    }
+
+    #[test]
+    fn test_create() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+
+        let mut log = OpLog::new();
+        let created = log.create(dir.join("MAPINFO"), b"map MAP01");
+        let again = log.create(dir.join("MAPINFO"), b"");
+        let contents = std::fs::read(dir.join("MAPINFO")).unwrap();
+
+        assert!(matches!(created, Ok(FileOp::Create { .. })));
+        assert!(matches!(again, Err(Error::AlreadyExists(_))));
+        assert_eq!(contents, b"map MAP01");
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+        std::fs::write(dir.join("MAP01.wad"), b"PWAD").unwrap();
+
+        let mut log = OpLog::new();
+        let (from, to) = (dir.join("MAP01.wad"), dir.join("MAP02.wad"));
+        log.rename(&from, &to).unwrap();
+        let undone = log.undo();
+        let redone = log.redo();
+        let nothing = log.redo();
+
+        // the file changed after the rename
+        std::fs::write(&to, b"IWAD and more").unwrap();
+        let changed = log.undo();
+        let still_logged = log.can_undo();
+
+        assert!(matches!(undone, Ok(Some(FileOp::Rename { to, .. })) if to == from));
+        assert!(matches!(redone, Ok(Some(FileOp::Rename { .. }))));
+        assert!(matches!(nothing, Ok(None)));
+        assert!(matches!(changed, Err(Error::Changed(path)) if path == to));
+        assert!(still_logged);
+    }
+
+    #[test]
+    fn test_memory_fs() {
+        let fs = Arc::new(MemoryFs::new());
+        fs.create_dir_all("/doom/maps");
+        fs.write(Path::new("/doom/maps/MAP01.wad"), b"PWAD")
+            .unwrap();
+
+        let mut log = OpLog::new().with_fs(fs.clone());
+        log.create("/doom/MAPINFO", b"map MAP01").unwrap();
+        let to = copy_path_with(&*fs, Path::new("/doom/maps"));
+        log.copy("/doom/maps", &to).unwrap();
+        log.rename("/doom/maps/MAP01.wad", "/doom/maps/MAP02.wad")
+            .unwrap();
+        log.undo().unwrap();
+
+        assert_eq!(to, Path::new("/doom/maps (copy)"));
+        assert_eq!(fs.read(Path::new("/doom/MAPINFO")).unwrap(), b"map MAP01");
+        assert_eq!(
+            fs.read(Path::new("/doom/maps (copy)/MAP01.wad")).unwrap(),
+            b"PWAD"
+        );
+        assert!(fs.entry(Path::new("/doom/maps/MAP01.wad")).is_ok());
+        assert!(fs.entry(Path::new("/doom/maps/MAP02.wad")).is_err());
+    }
+
+    #[test]
+    fn test_memory_fs_trash() {
+        // the same path on the local disk must not be touched
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("MAP01.wad");
+        std::fs::write(&path, b"PWAD").unwrap();
+
+        let fs = Arc::new(MemoryFs::new());
+        fs.create_dir_all(temp.path());
+        fs.write(&path, b"IWAD").unwrap();
+
+        let mut log = OpLog::new().with_fs(fs.clone());
+        log.delete(&path).unwrap();
+        let deleted = fs.entry(&path).is_err();
+        log.undo().unwrap();
+        let restored = fs.read(&path).unwrap();
+        log.redo().unwrap();
+
+        assert!(deleted);
+        assert_eq!(restored, b"IWAD");
+        assert!(fs.entry(&path).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"PWAD");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_symlinks() {
+        let temp = tempfile::tempdir().unwrap();
+        let (root, home) = (temp.path().join("doom"), temp.path().join("home"));
+        std::fs::create_dir_all(root.join("maps")).unwrap();
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::write(root.join("maps/MAP01.wad"), b"PWAD").unwrap();
+        std::os::unix::fs::symlink("maps", root.join("levels")).unwrap();
+        std::os::unix::fs::symlink(".", root.join("loop")).unwrap();
+        std::os::unix::fs::symlink(&home, root.join("home")).unwrap();
+
+        let to = temp.path().join("doom (copy)");
+        OpLog::new().copy(&root, &to).unwrap();
+
+        assert_eq!(std::fs::read(to.join("maps/MAP01.wad")).unwrap(), b"PWAD");
+        for (link, target) in [
+            ("levels", Path::new("maps")),
+            ("loop", Path::new(".")),
+            ("home", &home),
+        ] {
+            let link = to.join(link);
+            assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+            assert_eq!(std::fs::read_link(&link).unwrap(), target);
+        }
+    }
+
+    #[test]
+    fn test_copy_path() {
+        let taken = [
+            PathBuf::from("/var/doom2.wad"),
+            PathBuf::from("/var/doom2 (copy).wad"),
+            PathBuf::from("/var/maps"),
+        ];
+        let exists = |path: &Path| taken.iter().any(|taken| taken == path);
+
+        assert_eq!(
+            free_copy_path(Path::new("/var/doom.wad"), exists),
+            Path::new("/var/doom.wad")
+        );
+        assert_eq!(
+            free_copy_path(Path::new("/var/doom2.wad"), exists),
+            Path::new("/var/doom2 (copy 2).wad")
+        );
+        assert_eq!(
+            free_copy_path(Path::new("/var/maps"), exists),
+            Path::new("/var/maps (copy)")
+        );
+    }
+
+    #[test]
+    fn test_make_writable() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+        let path = dir.join("IWAD.wad");
+        std::fs::write(&path, b"IWAD").unwrap();
+
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        let read_only = Node::new(&path).is_read_only();
+        make_writable(&path).unwrap();
+        let writable = !Node::new(&path).is_read_only();
+
+        assert!(read_only);
+        assert!(writable);
+    }
+
+    #[test]
+    fn test_format_path() {
+        let (root, path) = (
+            Path::new("/var/doom"),
+            Path::new("/var/doom/maps/MAP01.wad"),
+        );
+
+        assert_eq!(format_path("{rel}", root, path), "maps/MAP01.wad");
+        assert_eq!(
+            format_path("{name} in {dir} ({path}) {line}", root, path),
+            "MAP01.wad in maps (/var/doom/maps/MAP01.wad) {line}"
+        );
+        assert_eq!(format_path("{rel}", root, Path::new("/opt")), "/opt");
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("doom2.wad").is_ok());
+        assert!(check_name(".doomrc").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("..").is_err());
+        assert!(check_name("games/doom2.wad").is_err());
+    }
+
+    #[test]
+    fn test_protection() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+        std::fs::create_dir_all(dir.join("maps")).unwrap();
+        std::fs::write(dir.join("maps/MAP01.wad"), b"PWAD").unwrap();
+
+        std::fs::create_dir_all(dir.join("mods/brutal/.git")).unwrap();
+        std::fs::create_dir_all(dir.join("textures")).unwrap();
+
+        let protection = Protection::new(&dir, [dir.clone()], ["maps/*.wad"]).unwrap();
+        assert!(protection.is_protected(&LocalFs, &dir));
+        assert!(protection.is_protected(&LocalFs, &dir.join(".git")));
+        assert!(protection.is_protected(&LocalFs, &dir.join("maps/MAP01.wad")));
+        assert!(!protection.is_protected(&LocalFs, &dir.join("textures")));
+        // ancestors of protected paths
+        assert!(protection.is_protected(&LocalFs, &dir.join("maps")));
+        assert!(protection.is_protected(&LocalFs, &dir.join("mods")));
+        // links are not followed
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("mods"), dir.join("textures/mods")).unwrap();
+            assert!(!protection.is_protected(&LocalFs, &dir.join("textures")));
+        }
+
+        let mut log = OpLog::new().with_protection(protection);
+        let (from, to) = (dir.join("maps/MAP01.wad"), dir.join("maps/MAP02.wad"));
+        let unconfirmed = log.rename(&from, &to);
+        let wrong_name = log.confirm(&from, "MAP02.wad");
+        log.confirm(&from, "MAP01.wad").unwrap();
+        let confirmed = log.rename(&from, &to);
+        // confirmations do not carry over
+        let again = log.rename(&to, &from);
+
+        assert!(matches!(unconfirmed, Err(Error::Protected(_))));
+        assert!(matches!(wrong_name, Err(Error::Protected(_))));
+        assert!(confirmed.is_ok());
+        assert!(matches!(again, Err(Error::Protected(_))));
+    }
+}
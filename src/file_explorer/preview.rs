@@ -0,0 +1,367 @@
+//! Quick, read-only file previews.
+
+use floem::prelude::*;
+use floem::views::VirtualVector;
+
+use std::cell::RefCell;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::locale::{tr, tr_args};
+
+use super::fs::{FsProvider, LocalFs};
+
+/// The most bytes of a text file shown in a preview.
+const MAX_TEXT_LEN: u64 = 64 * 1024;
+/// The bytes shown on each row of the hex viewer.
+const HEX_ROW_LEN: u64 = 16;
+/// The bytes the hex viewer reads from disk at a time.
+const HEX_CHUNK_LEN: u64 = 64 * 1024;
+/// The zoom levels of the image viewer.
+const ZOOM_LEVELS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// The loaded contents of a preview.
+enum Preview {
+    Text {
+        text: String,
+        truncated: bool,
+    },
+    Image {
+        data: Vec<u8>,
+        /// The width of the image in pixels, if its format is known.
+        width: Option<u32>,
+    },
+    Svg(String),
+    /// A binary file, `len` bytes long. Its contents are read as they are
+    /// scrolled into view.
+    Binary {
+        source: HexSource,
+        len: u64,
+    },
+    Error(io::Error),
+}
+
+impl Preview {
+    /// Loads a preview of the file at `path` in `fs`.
+    fn load(fs: &Arc<dyn FsProvider>, path: &Path) -> Preview {
+        Preview::try_load(fs, path).unwrap_or_else(Preview::Error)
+    }
+
+    fn try_load(fs: &Arc<dyn FsProvider>, path: &Path) -> io::Result<Preview> {
+        match Kind::of(path) {
+            Kind::Image => return Ok(Preview::image(fs.read(path)?)),
+            Kind::Svg => {
+                let data = fs.read(path)?;
+                return String::from_utf8(data)
+                    .map(Preview::Svg)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+            Kind::Other => (),
+        }
+
+        let buf = fs.read_range(path, 0, MAX_TEXT_LEN + 1)?;
+        if buf.contains(&0) {
+            return Ok(Preview::Binary {
+                source: HexSource {
+                    fs: fs.clone(),
+                    path: path.to_owned(),
+                },
+                len: fs.metadata(path)?.len,
+            });
+        }
+        Ok(Preview::text(buf))
+    }
+
+    fn image(data: Vec<u8>) -> Preview {
+        let width = image::ImageReader::new(io::Cursor::new(&data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .map(|(width, _)| width);
+        Preview::Image { data, width }
+    }
+
+    fn text(mut buf: Vec<u8>) -> Preview {
+        let truncated = buf.len() as u64 > MAX_TEXT_LEN;
+        buf.truncate(MAX_TEXT_LEN as usize);
+        Preview::Text {
+            text: String::from_utf8_lossy(&buf).into_owned(),
+            truncated,
+        }
+    }
+}
+
+/// The kind of a file, guessed from its extension.
+enum Kind {
+    Image,
+    Svg,
+    Other,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Kind {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("png" | "jpg" | "jpeg" | "gif" | "bmp" | "ico" | "webp") => Kind::Image,
+            Some("svg") => Kind::Svg,
+            _ => Kind::Other,
+        }
+    }
+}
+
+/// A read-only preview of the file at `path`.
+pub fn preview_view(path: PathBuf) -> impl IntoView {
+    preview_view_with(Arc::new(LocalFs), path)
+}
+
+/// Like [`preview_view`], but reads the file from `fs`.
+pub fn preview_view_with(fs: Arc<dyn FsProvider>, path: PathBuf) -> impl IntoView {
+    let title = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let content = match Preview::load(&fs, &path) {
+        Preview::Text { text, truncated } => {
+            let text = if truncated { text + "\n…" } else { text };
+            label(move || text.clone())
+                .style(|s| s.font_family("monospace".to_owned()).padding(4.0))
+                .into_any()
+        }
+        Preview::Image { data, width } => image_view(data, width).into_any(),
+        Preview::Svg(data) => svg(data).style(|s| s.size_full()).into_any(),
+        Preview::Binary { source, len } => hex_view(source, len).into_any(),
+        Preview::Error(err) => {
+            message(tr_args("Cannot preview: {error}", &[("error", &err)])).into_any()
+        }
+    };
+
+    (
+        label(move || tr_args("{name} (Esc to close)", &[("name", &title)]))
+            .style(|s| s.padding(4.0).border_bottom(1.0).width_full()),
+        scroll(content).style(|s| s.flex_grow(1.0).min_height(0.0).width_full()),
+    )
+        .v_stack()
+        .style(|s| s.size_full().background(Color::WHITE))
+}
+
+/// An image, fit to the preview or zoomed to a multiple of its size.
+///
+/// Zooming needs the width of the image, so only fitting is offered if it is
+/// not known.
+fn image_view(data: Vec<u8>, width: Option<u32>) -> impl IntoView {
+    // `None` fits the image to the preview
+    let zoom = create_rw_signal(None::<usize>);
+    let step = move |by: isize| {
+        zoom.update(|zoom| {
+            let level = zoom.unwrap_or(2) as isize + by;
+            *zoom = Some(level.clamp(0, ZOOM_LEVELS.len() as isize - 1) as usize);
+        })
+    };
+
+    let controls = (
+        button("−").action(move || step(-1)),
+        button("+").action(move || step(1)),
+        button(label(|| tr("Fit"))).action(move || zoom.set(None)),
+        label(move || match zoom.get() {
+            Some(level) => format!("{}%", ZOOM_LEVELS[level] * 100.0),
+            None => tr("Fit"),
+        }),
+    )
+        .h_stack()
+        .style(move |s| {
+            s.gap(2.0)
+                .padding(4.0)
+                .items_center()
+                .apply_if(width.is_none(), |s| s.hide())
+        });
+
+    let image = img(move || data.clone()).style(move |s| match (zoom.get(), width) {
+        (Some(level), Some(width)) => s.width(width as f64 * ZOOM_LEVELS[level]),
+        _ => s.max_width_full(),
+    });
+
+    (controls, image).v_stack()
+}
+
+/// A read-only hex dump of `source`, `len` bytes long.
+fn hex_view(source: HexSource, len: u64) -> impl IntoView {
+    let file = Rc::new(HexFile::new(source));
+    let rows = HexRows(len.div_ceil(HEX_ROW_LEN) as usize);
+
+    virtual_list(
+        VirtualDirection::Vertical,
+        VirtualItemSize::Fixed(Box::new(|| 16.0)),
+        move || rows,
+        |row| *row,
+        move |row| {
+            let text = file
+                .row(row as u64)
+                .unwrap_or_else(|err| format!("{:08x}  {}", row as u64 * HEX_ROW_LEN, err));
+            label(move || text.clone()).style(|s| s.height(16.0))
+        },
+    )
+    .style(|s| {
+        s.flex_col()
+            .padding(4.0)
+            .font_family("monospace".to_owned())
+    })
+}
+
+/// The row indices of a hex dump.
+#[derive(Clone, Copy)]
+struct HexRows(usize);
+
+impl VirtualVector<usize> for HexRows {
+    fn total_len(&self) -> usize {
+        self.0
+    }
+
+    fn slice(&mut self, range: Range<usize>) -> impl Iterator<Item = usize> {
+        range
+    }
+}
+
+/// The file the hex viewer reads bytes from.
+struct HexSource {
+    fs: Arc<dyn FsProvider>,
+    path: PathBuf,
+}
+
+/// A file read for the hex viewer, one chunk at a time.
+struct HexFile {
+    source: HexSource,
+    /// The last chunk read, and its offset.
+    chunk: RefCell<Option<(u64, Rc<[u8]>)>>,
+}
+
+impl HexFile {
+    fn new(source: HexSource) -> HexFile {
+        HexFile {
+            source,
+            chunk: RefCell::new(None),
+        }
+    }
+
+    /// Formats a row of the hex dump.
+    fn row(&self, row: u64) -> io::Result<String> {
+        let offset = row * HEX_ROW_LEN;
+        let chunk_offset = offset - offset % HEX_CHUNK_LEN;
+
+        let cached = self
+            .chunk
+            .borrow()
+            .as_ref()
+            .filter(|(offset, _)| *offset == chunk_offset)
+            .map(|(_, chunk)| chunk.clone());
+        let chunk = match cached {
+            Some(chunk) => chunk,
+            None => {
+                let chunk = self.read_chunk(chunk_offset)?;
+                *self.chunk.borrow_mut() = Some((chunk_offset, chunk.clone()));
+                chunk
+            }
+        };
+
+        let start = ((offset - chunk_offset) as usize).min(chunk.len());
+        let end = (start + HEX_ROW_LEN as usize).min(chunk.len());
+        Ok(hex_row(offset, &chunk[start..end]))
+    }
+
+    fn read_chunk(&self, offset: u64) -> io::Result<Rc<[u8]>> {
+        let HexSource { fs, path } = &self.source;
+        Ok(fs.read_range(path, offset, HEX_CHUNK_LEN)?.into())
+    }
+}
+
+/// Formats a row of a hex dump with offset, hex and ASCII columns.
+fn hex_row(offset: u64, bytes: &[u8]) -> String {
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let ascii = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect::<String>();
+
+    format!(
+        "{:08x}  {:<width$}  {}",
+        offset,
+        hex,
+        ascii,
+        width = HEX_ROW_LEN as usize * 3 - 1
+    )
+}
+
+pub(super) fn message(text: impl Into<String>) -> impl IntoView {
+    let text = text.into();
+    label(move || text.clone()).style(|s| s.padding(4.0).color(Color::GRAY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_explorer::fs::MemoryFs;
+
+    #[test]
+    fn test_load() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+        std::fs::write(dir.join("MAPINFO.txt"), "map MAP01 \"Entryway\"").unwrap();
+        std::fs::write(dir.join("MAP01.wad"), b"PWAD\0\0\0\0").unwrap();
+
+        let fs: Arc<dyn FsProvider> = Arc::new(LocalFs);
+        let text = Preview::load(&fs, &dir.join("MAPINFO.txt"));
+        let binary = Preview::load(&fs, &dir.join("MAP01.wad"));
+        let missing = Preview::load(&fs, &dir.join("MAP02.wad"));
+
+        assert!(matches!(
+            text,
+            Preview::Text { text, truncated: false } if text == "map MAP01 \"Entryway\""
+        ));
+        assert!(matches!(binary, Preview::Binary { len: 8, .. }));
+        assert!(matches!(missing, Preview::Error(_)));
+    }
+
+    #[test]
+    fn test_load_with() {
+        let memory = Arc::new(MemoryFs::new());
+        memory.create_dir_all("/doom");
+        memory
+            .write(Path::new("/doom/MAP01.wad"), b"PWAD\0\0\0\0")
+            .unwrap();
+
+        let fs: Arc<dyn FsProvider> = memory;
+        let Preview::Binary { source, len } = Preview::load(&fs, Path::new("/doom/MAP01.wad"))
+        else {
+            panic!("expected a binary preview");
+        };
+        assert_eq!(len, 8);
+        assert_eq!(
+            HexFile::new(source).row(0).unwrap(),
+            hex_row(0, b"PWAD\0\0\0\0")
+        );
+    }
+
+    #[test]
+    fn test_hex_row() {
+        assert_eq!(
+            hex_row(0x10, b"PWAD\0\x01 MAP01"),
+            "00000010  50 57 41 44 00 01 20 4d 41 50 30 31              PWAD.. MAP01"
+        );
+    }
+}
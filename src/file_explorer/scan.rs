@@ -0,0 +1,196 @@
+//! Background project scanning.
+
+use floem::ext_event::create_signal_from_channel;
+use floem::prelude::*;
+use floem::reactive::create_effect;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::data::{Node, Tree};
+use super::fs::{FsProvider, LocalFs};
+
+/// The most nodes sent in a single batch.
+const BATCH_SIZE: usize = 512;
+/// The longest a batch is held before being sent, so the tree fills in
+/// steadily even on slow disks.
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An event sent from the scanning thread.
+enum ScanEvent {
+    /// A batch of newly discovered nodes.
+    Batch(Vec<Node>),
+    /// The scan has finished.
+    Done,
+}
+
+/// Scans the directory at `root` on the local disk on a background thread,
+/// streaming the discovered entries into `tree`.
+///
+/// Returns a signal that is `true` while the scan is running.
+pub fn scan(root: impl Into<PathBuf>, tree: RwSignal<Tree>) -> RwSignal<bool> {
+    scan_with(LocalFs::shared(), root, tree)
+}
+
+/// Like [`scan`], but reads from `fs`.
+pub fn scan_with(
+    fs: Arc<dyn FsProvider>,
+    root: impl Into<PathBuf>,
+    tree: RwSignal<Tree>,
+) -> RwSignal<bool> {
+    let root = root.into();
+    let scanning = create_rw_signal(true);
+
+    let (tx, rx) = crossbeam_channel::unbounded();
+    std::thread::spawn(move || {
+        let mut batch = Vec::new();
+        let mut last_sent = Instant::now();
+
+        // skip the root, it is already in the tree
+        for node in walk(&fs, &root).skip(1) {
+            batch.push(node);
+
+            if batch.len() >= BATCH_SIZE || last_sent.elapsed() >= BATCH_INTERVAL {
+                if tx
+                    .send(ScanEvent::Batch(std::mem::take(&mut batch)))
+                    .is_err()
+                {
+                    return;
+                }
+                last_sent = Instant::now();
+            }
+        }
+
+        let _ = tx.send(ScanEvent::Batch(batch));
+        let _ = tx.send(ScanEvent::Done);
+    });
+
+    let events = create_signal_from_channel(rx);
+    create_effect(move |_| {
+        events.with(|event| match event {
+            Some(ScanEvent::Batch(batch)) => tree.update(|tree| {
+                for node in batch {
+                    tree.create(node.clone());
+                }
+            }),
+            Some(ScanEvent::Done) => scanning.set(false),
+            None => (),
+        })
+    });
+
+    scanning
+}
+
+/// Loads the file or directory at `path` on the local disk into `tree`,
+/// blocking until it has been read.
+///
+/// Does nothing if `path` cannot be represented in the tree.
+pub fn load(tree: &mut Tree, path: &Path) {
+    load_with(&LocalFs::shared(), tree, path)
+}
+
+/// Like [`load`], but reads from `fs`.
+pub fn load_with(fs: &Arc<dyn FsProvider>, tree: &mut Tree, path: &Path) {
+    if !path.starts_with(tree.root().path()) {
+        return;
+    }
+
+    for node in walk(fs, path) {
+        tree.create(node);
+    }
+}
+
+/// Walks the entry at `path` and everything below it, skipping entries that
+/// cannot be read.
+fn walk<'a>(fs: &'a Arc<dyn FsProvider>, path: &Path) -> impl Iterator<Item = Node> + 'a {
+    fs.walk(path).filter_map(|entry| {
+        let entry = entry.ok()?;
+        Some(Node {
+            is_dir: entry.is_dir,
+            is_open: true,
+            link_target: entry.link_target,
+            fs: fs.clone(),
+            ..Node::new(entry.path)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_explorer::fs::MemoryFs;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_symlink_cycle() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().to_owned();
+        std::fs::create_dir_all(root.join("maps")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("maps/loop")).unwrap();
+
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new(&root)
+        });
+        load(&mut tree, &root);
+
+        let ix = tree.find(root.join("maps/loop")).unwrap();
+        let node = tree.get(ix).unwrap();
+        assert!(node.is_symlink());
+        assert!(node.first_child.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_symlink_outside() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().to_owned();
+        let (root, home) = (dir.join("doom"), dir.join("home"));
+        std::fs::create_dir_all(root.join("maps")).unwrap();
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::write(root.join("maps/MAP01.wad"), b"PWAD").unwrap();
+        std::fs::write(home.join("notes.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(root.join("maps"), root.join("levels")).unwrap();
+        std::os::unix::fs::symlink(&home, root.join("home")).unwrap();
+
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new(&root)
+        });
+        load(&mut tree, &root);
+
+        // links inside the root are followed, links out of it are not
+        assert!(tree.find(root.join("levels/MAP01.wad")).is_some());
+        let ix = tree.find(root.join("home")).unwrap();
+        let node = tree.get(ix).unwrap();
+        assert!(node.is_symlink());
+        assert!(node.first_child.is_none());
+    }
+
+    #[test]
+    fn test_load_with() {
+        let memory = MemoryFs::new();
+        memory.create_dir_all("/doom/maps");
+        memory
+            .write(Path::new("/doom/maps/MAP01.wad"), b"PWAD")
+            .unwrap();
+        let fs: Arc<dyn FsProvider> = Arc::new(memory);
+
+        let mut tree = Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new("/doom")
+        });
+        load_with(&fs, &mut tree, Path::new("/doom/maps"));
+
+        let ix = tree.find("/doom/maps").unwrap();
+        assert!(tree.get(ix).unwrap().is_dir);
+        // metadata is read from the same provider
+        let ix = tree.find("/doom/maps/MAP01.wad").unwrap();
+        assert_eq!(tree.get(ix).unwrap().metadata().unwrap().len, 4);
+        assert_eq!(tree.root().children_open_count, 2);
+    }
+}
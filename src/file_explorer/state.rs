@@ -0,0 +1,799 @@
+//! Explorer state shared between views.
+
+use floem::ext_event::create_ext_action;
+use floem::prelude::*;
+use floem::reactive::{create_effect, untrack, with_scope, Scope};
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::ExplorerSettings;
+use crate::keymap::Keymap;
+use crate::locale::tr;
+use crate::notify;
+
+use super::archive::{mark_members, ArchiveFs};
+use super::badges;
+use super::data::{Node, Tree, TreeIndex, TreeState};
+use super::favorites::Favorites;
+use super::fs::{FsProvider, LocalFs};
+use super::list::{ScrollBehavior, SortMode};
+use super::ops::{check_name, copy_path, make_writable, Error, FileOp, OpLog, Protection};
+use super::scan::{load_with, scan};
+
+/// The most nodes kept in the [`History`].
+const MAX_HISTORY: usize = 50;
+
+/// The nodes a pane jumped to, like by clicking them or picking a search
+/// result, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct History {
+    visited: Vec<PathBuf>,
+    /// The index of the current node in `visited`.
+    current: usize,
+}
+
+impl History {
+    /// Records a jump to `path`, forgetting the nodes gone back from.
+    pub fn visit(&mut self, path: PathBuf) {
+        if self.visited.get(self.current) == Some(&path) {
+            return;
+        }
+
+        self.visited.truncate(self.current + 1);
+        self.visited.push(path);
+        if self.visited.len() > MAX_HISTORY {
+            self.visited.remove(0);
+        }
+        self.current = self.visited.len() - 1;
+    }
+
+    /// Goes back to the node visited before the current one.
+    pub fn back(&mut self) -> Option<&Path> {
+        self.current = self.current.checked_sub(1)?;
+        self.visited.get(self.current).map(PathBuf::as_path)
+    }
+
+    /// Goes forward to the node visited after the current one.
+    pub fn forward(&mut self) -> Option<&Path> {
+        if self.current + 1 >= self.visited.len() {
+            return None;
+        }
+        self.current += 1;
+        self.visited.get(self.current).map(PathBuf::as_path)
+    }
+}
+
+/// A file open in a tab of the preview.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreviewTab {
+    pub path: PathBuf,
+    /// `false` for the preview tab, which the next file previewed replaces.
+    pub pinned: bool,
+}
+
+/// A single explorer pane, showing one tree.
+#[derive(Clone, Copy)]
+pub struct Pane {
+    pub tree: RwSignal<Tree>,
+    /// `true` while the tree is still being scanned.
+    pub scanning: RwSignal<bool>,
+    /// The path of the selected node, if any.
+    pub selected: RwSignal<Option<PathBuf>>,
+    /// The path of the node under the pointer, if any.
+    pub hovered: RwSignal<Option<PathBuf>>,
+    /// The file being previewed, if any. It is one of the [`Pane::tabs`].
+    pub preview: RwSignal<Option<PathBuf>>,
+    /// The files open in preview tabs, in the order they were opened.
+    pub tabs: RwSignal<Vec<PreviewTab>>,
+    /// The nodes jumped to, for going back and forward.
+    pub history: RwSignal<History>,
+    /// The path last passed to [`Pane::reveal`], which the view selects and
+    /// scrolls into view.
+    pub revealed: RwSignal<Option<PathBuf>>,
+    /// The node last passed to [`Pane::scroll_to`], which the view scrolls
+    /// into view.
+    pub scroll_target: RwSignal<Option<(TreeIndex, ScrollBehavior)>>,
+    /// The path of the node being renamed, if any.
+    pub renaming: RwSignal<Option<PathBuf>>,
+    /// `true` to show the size and modified time of each node.
+    pub details: RwSignal<bool>,
+    /// The order siblings are listed in.
+    pub sort: RwSignal<SortMode>,
+    /// `true` to list every file in a flat list instead of a tree.
+    pub flat: RwSignal<bool>,
+    /// The old and new files being compared, if any.
+    pub diff: RwSignal<Option<(PathBuf, PathBuf)>>,
+    /// `true` while the Find by Pattern search is open.
+    pub finding: RwSignal<bool>,
+}
+
+impl Pane {
+    /// Opens a pane at `root`, scanning it in the background.
+    pub fn open(root: impl Into<PathBuf>) -> Pane {
+        let root = root.into();
+        let tree = create_rw_signal(Tree::new(Node {
+            is_dir: true,
+            is_open: true,
+            ..Node::new(root.clone())
+        }));
+        let scanning = scan(root, tree);
+
+        Pane {
+            tree,
+            scanning,
+            selected: create_rw_signal(None),
+            hovered: create_rw_signal(None),
+            preview: create_rw_signal(None),
+            tabs: create_rw_signal(Vec::new()),
+            history: create_rw_signal(History::default()),
+            revealed: create_rw_signal(None),
+            scroll_target: create_rw_signal(None),
+            renaming: create_rw_signal(None),
+            details: create_rw_signal(false),
+            sort: create_rw_signal(SortMode::default()),
+            flat: create_rw_signal(false),
+            diff: create_rw_signal(None),
+            finding: create_rw_signal(false),
+        }
+    }
+
+    /// Opens every ancestor of the node at `path`, then selects it and
+    /// scrolls it into view.
+    pub fn reveal(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+
+        let found = self
+            .tree
+            .try_update(|tree| tree.reveal(&path))
+            .flatten()
+            .is_some();
+        if found {
+            self.revealed.set(Some(path));
+        }
+    }
+
+    /// Opens or closes the directory at `path`. The root is always open.
+    pub fn set_open(&self, path: &Path, is_open: bool) {
+        self.tree.update(|tree| {
+            let Some(ix) = tree.find(path).filter(|ix| *ix != TreeIndex::ROOT) else {
+                return;
+            };
+            if tree.get(ix).is_some_and(|node| node.is_dir) {
+                tree.set_open(ix, is_open);
+            }
+        });
+    }
+
+    /// Opens the directory at `path` if it is closed, or else closes it.
+    pub fn toggle_open(&self, path: &Path) {
+        let is_open = self.tree.with_untracked(|tree| {
+            tree.find(path)
+                .and_then(|ix| tree.get(ix))
+                .is_some_and(|node| node.is_open)
+        });
+        self.set_open(path, !is_open);
+    }
+
+    /// Scrolls the node at `ix` into view, if it is listed.
+    pub fn scroll_to(&self, ix: TreeIndex, behavior: ScrollBehavior) {
+        self.scroll_target.set(Some((ix, behavior)));
+    }
+
+    /// Gets the open directories and selection of the pane, to be restored
+    /// with [`Pane::restore_state`].
+    pub fn state(&self) -> TreeState {
+        self.tree.with_untracked(|tree| {
+            let root = tree.root().path();
+            TreeState {
+                selected: self.selected.with_untracked(|selected| {
+                    Some(selected.as_ref()?.strip_prefix(root).ok()?.to_owned())
+                }),
+                ..tree.state()
+            }
+        })
+    }
+
+    /// Reopens the directories in `state` and reveals its selection, once
+    /// the pane has been scanned.
+    pub fn restore_state(&self, state: TreeState) {
+        let pane = *self;
+        // waits for the scan, then is disposed
+        let cx = Scope::new();
+        with_scope(cx, || {
+            create_effect(move |_| {
+                if pane.scanning.get() {
+                    return;
+                }
+
+                untrack(|| {
+                    pane.tree.update(|tree| tree.restore_state(&state));
+                    if let Some(selected) = &state.selected {
+                        let root = pane.tree.with(|tree| tree.root().path().to_owned());
+                        pane.reveal(root.join(selected));
+                    }
+                });
+                cx.dispose();
+            });
+        });
+    }
+
+    /// Reveals the node at `path`, remembering it in the history.
+    pub fn jump_to(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.history.update(|history| history.visit(path.clone()));
+        self.reveal(path);
+    }
+
+    /// Reveals the node visited before the current one, if any.
+    pub fn go_back(&self) {
+        let path = self
+            .history
+            .try_update(|history| history.back().map(Path::to_owned))
+            .flatten();
+        if let Some(path) = path {
+            self.reveal(path);
+        }
+    }
+
+    /// Reveals the node visited after the current one, if any.
+    pub fn go_forward(&self) {
+        let path = self
+            .history
+            .try_update(|history| history.forward().map(Path::to_owned))
+            .flatten();
+        if let Some(path) = path {
+            self.reveal(path);
+        }
+    }
+
+    /// Rescans the tree in the background, then merges the scan into the
+    /// tree so open directories stay open.
+    pub fn refresh(&self) {
+        let root = self
+            .tree
+            .with_untracked(|tree| tree.root().path().to_owned());
+        let (tree, scanning) = (self.tree, self.scanning);
+        scanning.set(true);
+
+        // the scan is disposed of once it is merged
+        let cx = Scope::new();
+        with_scope(cx, || {
+            let fresh = create_rw_signal(Tree::new(Node {
+                is_dir: true,
+                is_open: true,
+                ..Node::new(root.clone())
+            }));
+            let fresh_scanning = scan(root, fresh);
+            create_effect(move |_| {
+                if !fresh_scanning.get() {
+                    fresh.with_untracked(|fresh| {
+                        tree.update(|tree| {
+                            tree.merge_from(fresh);
+                            badges::recount(tree);
+                        })
+                    });
+                    scanning.set(false);
+                    cx.dispose();
+                }
+            });
+        });
+    }
+
+    /// Previews the file at `path` in a tab, reusing its tab if it has one.
+    ///
+    /// Unless `pinned`, the file replaces the file in the preview tab, so
+    /// browsing files does not pile up tabs.
+    pub fn open_tab(&self, path: impl Into<PathBuf>, pinned: bool) {
+        let path = path.into();
+        self.tabs.update(|tabs| open_tab(tabs, &path, pinned));
+        self.preview.set(Some(path));
+    }
+
+    /// Keeps the tab of the file at `path` open when another file is
+    /// previewed.
+    pub fn pin_tab(&self, path: &Path) {
+        self.tabs.update(|tabs| {
+            if let Some(tab) = tabs.iter_mut().find(|tab| tab.path == path) {
+                tab.pinned = true;
+            }
+        });
+    }
+
+    /// Closes the tab of the file at `path`. If it was shown, the tab after
+    /// it is shown instead.
+    pub fn close_tab(&self, path: &Path) {
+        let Some(index) = self
+            .tabs
+            .with_untracked(|tabs| tabs.iter().position(|tab| tab.path == path))
+        else {
+            return;
+        };
+        self.tabs.update(|tabs| {
+            tabs.remove(index);
+        });
+
+        if self
+            .preview
+            .with_untracked(|preview| preview.as_deref() == Some(path))
+        {
+            let next = self.tabs.with_untracked(|tabs| {
+                tabs.get(index)
+                    .or_else(|| tabs.last())
+                    .map(|tab| tab.path.clone())
+            });
+            self.preview.set(next);
+        }
+    }
+
+    /// Reveals the node at `path`, and starts renaming it.
+    pub fn start_rename(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.reveal(path.clone());
+        if self
+            .revealed
+            .with_untracked(|revealed| revealed.as_ref() == Some(&path))
+        {
+            self.renaming.set(Some(path));
+        }
+    }
+
+    /// Gets the path the node at `path` would be renamed to if it were given
+    /// `name`.
+    ///
+    /// Fails if `name` is not a valid file name, or if a sibling already has
+    /// that name.
+    pub fn rename_target(&self, path: &Path, name: &str) -> Result<PathBuf, Error> {
+        check_name(name)?;
+
+        let to = path.with_file_name(name);
+        if to != path && self.tree.with_untracked(|tree| tree.find(&to).is_some()) {
+            return Err(Error::AlreadyExists(to));
+        }
+        Ok(to)
+    }
+}
+
+/// Files cut or copied in the explorer, waiting to be pasted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileClipboard {
+    pub paths: Vec<PathBuf>,
+    /// `true` if the files are moved when pasted, instead of copied.
+    pub cut: bool,
+}
+
+/// State shared between every explorer pane.
+#[derive(Clone, Copy)]
+pub struct ExplorerState {
+    /// The root of the workspace, which the settings are loaded for.
+    pub root: RwSignal<PathBuf>,
+    /// The open panes. There are at most two.
+    pub panes: RwSignal<Vec<Pane>>,
+    pub op_log: RwSignal<OpLog>,
+    /// The path of the row being dragged, if any.
+    pub dragging: RwSignal<Option<PathBuf>>,
+    pub keymap: RwSignal<Keymap>,
+    /// An operation on a protected path, waiting to be confirmed.
+    pub confirming: RwSignal<Option<FileOp>>,
+    pub settings: RwSignal<ExplorerSettings>,
+    pub clipboard: RwSignal<Option<FileClipboard>>,
+    /// The file selected to be compared with another.
+    pub compare_with: RwSignal<Option<PathBuf>>,
+    /// The paths pinned above the tree, saved in the workspace.
+    pub favorites: RwSignal<Favorites>,
+}
+
+impl ExplorerState {
+    /// Creates a new `ExplorerState` with a single pane at `root`.
+    ///
+    /// The root is protected from being deleted or moved, along with any
+    /// paths protected in the settings. Favorites are loaded from, and saved
+    /// to, the workspace at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> ExplorerState {
+        let root = root.into();
+        let settings = create_rw_signal(ExplorerSettings::load(&root));
+        let op_log = create_rw_signal(OpLog::new());
+        let favorites = create_rw_signal(Favorites::load(&root));
+
+        create_effect({
+            let root = root.clone();
+            move |loaded: Option<()>| {
+                favorites.with(|favorites| {
+                    // only save changes
+                    if loaded.is_none() {
+                        return;
+                    }
+                    if let Err(err) = favorites.save(&root) {
+                        notify::error(tr("Failed to save favorites"), err.to_string(), Vec::new());
+                    }
+                })
+            }
+        });
+
+        create_effect({
+            let root = root.clone();
+            move |_| {
+                let protection = settings
+                    .with(|settings| Protection::new(&root, [root.clone()], &settings.protected));
+                match protection {
+                    Ok(protection) => op_log.update(|log| log.set_protection(protection)),
+                    Err(err) => tracing::warn!(%err, "invalid protected glob"),
+                }
+            }
+        });
+
+        ExplorerState {
+            root: create_rw_signal(root.clone()),
+            panes: create_rw_signal(vec![Pane::open(root)]),
+            op_log,
+            dragging: create_rw_signal(None),
+            keymap: create_rw_signal(load_keymap()),
+            confirming: create_rw_signal(None),
+            settings,
+            clipboard: create_rw_signal(None),
+            compare_with: create_rw_signal(None),
+            favorites,
+        }
+    }
+
+    /// Reads the settings and key bindings again, for after they were edited.
+    pub fn reload_settings(&self) {
+        let settings = self
+            .root
+            .with_untracked(|root| ExplorerSettings::load(root));
+        self.settings.set(settings);
+        self.keymap.set(load_keymap());
+    }
+
+    /// Checks if a second pane is open.
+    pub fn is_split(&self) -> bool {
+        self.panes.with(|panes| panes.len() > 1)
+    }
+
+    /// Opens a second pane at `root`, or closes it if one is already open.
+    pub fn toggle_split(&self, root: impl Into<PathBuf>) {
+        if self.is_split() {
+            self.panes.update(|panes| panes.truncate(1));
+        } else {
+            let pane = Pane::open(root);
+            self.panes.update(|panes| panes.push(pane));
+        }
+    }
+
+    /// Opens `root` in the second pane, replacing the second pane if one is
+    /// already open.
+    pub fn open_second(&self, root: impl Into<PathBuf>) {
+        let pane = Pane::open(root);
+        self.panes.update(|panes| {
+            panes.truncate(1);
+            panes.push(pane);
+        });
+    }
+
+    /// Opens a file or directory dropped from outside the app.
+    ///
+    /// Directories are opened in the second pane. Files are revealed in the
+    /// first pane showing them, and previewed.
+    pub fn open_dropped(&self, path: &Path) {
+        if path.is_dir() {
+            self.open_second(path);
+            return;
+        }
+
+        let panes = self.panes.get_untracked();
+        let shown_in = panes
+            .iter()
+            .find(|pane| pane.tree.with_untracked(|tree| tree.find(path).is_some()));
+        if let Some(pane) = shown_in {
+            pane.jump_to(path);
+        }
+        if let Some(pane) = shown_in.or(panes.first()) {
+            pane.open_tab(path, false);
+        }
+    }
+
+    /// Swaps the two panes.
+    pub fn swap_panes(&self) {
+        self.panes.update(|panes| panes.reverse());
+    }
+
+    /// Renames a file or directory.
+    ///
+    /// Renaming a protected path asks for a confirmation first.
+    pub fn rename(&self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) {
+        let (from, to) = (from.into(), to.into());
+        let result = self.op_log.try_update(|log| log.rename(&from, &to));
+        self.finish_guarded(result, FileOp::Rename { from, to }, "Failed to rename");
+    }
+
+    /// Copies a file or directory.
+    pub fn copy(&self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) {
+        let result = self.op_log.try_update(|log| log.copy(from, to));
+        self.finish(result, "Failed to copy");
+    }
+
+    /// Creates a new file holding `contents`.
+    pub fn create(&self, path: impl Into<PathBuf>, contents: &[u8]) {
+        let result = self.op_log.try_update(|log| log.create(path, contents));
+        self.finish(result, "Failed to create");
+    }
+
+    /// Copies a file or directory next to itself, then starts renaming the
+    /// copy in `pane`.
+    pub fn duplicate(&self, pane: Pane, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let to = copy_path(&path);
+        self.copy(path, to.clone());
+        pane.start_rename(to);
+    }
+
+    /// Creates a new file in `dir` from a template, then starts renaming it
+    /// in `pane`. Without a template, the file is empty.
+    pub fn new_file(&self, pane: Pane, dir: &Path, template: Option<&Path>) {
+        let (name, contents) = match template {
+            Some(template) => match std::fs::read(template) {
+                Ok(contents) => (template.file_name().unwrap_or_default(), contents),
+                Err(err) => {
+                    let body = format!("{}: {}", template.display(), err);
+                    notify::error(tr("Failed to read template"), body, Vec::new());
+                    return;
+                }
+            },
+            None => ("untitled".as_ref(), Vec::new()),
+        };
+
+        let path = copy_path(&dir.join(name));
+        self.create(path.clone(), &contents);
+        pane.start_rename(path);
+    }
+
+    /// Lists the members of the archive at `path` below it, in every pane.
+    ///
+    /// The archive is read on a background thread, and kept open for the
+    /// members to be read from.
+    pub fn browse_archive(&self, path: &Path) {
+        let state = *self;
+        let path = path.to_owned();
+        let finish = create_ext_action(Scope::current(), {
+            let path = path.clone();
+            move |archive: io::Result<ArchiveFs>| {
+                let archive: Arc<dyn FsProvider> = match archive {
+                    Ok(archive) => Arc::new(archive),
+                    Err(err) => {
+                        let body = format!("{}: {}", path.display(), err);
+                        notify::error(tr("Failed to open"), body, Vec::new());
+                        return;
+                    }
+                };
+
+                state.panes.with_untracked(|panes| {
+                    for pane in panes {
+                        pane.tree.update(|tree| {
+                            load_with(&archive, tree, &path);
+                            mark_members(tree, &path);
+                        });
+                    }
+                });
+            }
+        });
+
+        std::thread::spawn(move || finish(ArchiveFs::open(&LocalFs, &path)));
+    }
+
+    /// Lets the owner write to a read-only file or directory.
+    pub fn make_writable(&self, path: &Path) {
+        if let Err(err) = make_writable(path) {
+            let body = format!("{}: {}", path.display(), err);
+            notify::error(tr("Failed to make writable"), body, Vec::new());
+            return;
+        }
+
+        self.panes.with_untracked(|panes| {
+            for pane in panes {
+                pane.tree.update(|tree| {
+                    if let Some(ix) = tree.find(path) {
+                        tree.reload_metadata(ix);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Moves a file or directory to the trash.
+    ///
+    /// Deleting a protected path asks for a confirmation first.
+    pub fn delete(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let result = self.op_log.try_update(|log| log.delete(&path));
+        self.finish_guarded(result, FileOp::Delete { path }, "Failed to delete");
+    }
+
+    /// Runs the operation waiting to be confirmed, if `typed` is the file
+    /// name of its protected path.
+    ///
+    /// Returns `false` if the name does not match.
+    pub fn confirm(&self, typed: &str) -> bool {
+        let Some(op) = self.confirming.get_untracked() else {
+            return false;
+        };
+        let path = match &op {
+            FileOp::Rename { from, .. } => from,
+            FileOp::Delete { path } => path,
+            FileOp::Copy { .. } | FileOp::Create { .. } | FileOp::Restore { .. } => {
+                unreachable!("never protected")
+            }
+        };
+
+        let confirmed = self
+            .op_log
+            .try_update(|log| log.confirm(path, typed).is_ok())
+            .unwrap_or(false);
+        if !confirmed {
+            return false;
+        }
+
+        self.confirming.set(None);
+        match op {
+            FileOp::Rename { from, to } => self.rename(from, to),
+            FileOp::Delete { path } => self.delete(path),
+            FileOp::Copy { .. } | FileOp::Create { .. } | FileOp::Restore { .. } => (),
+        }
+        true
+    }
+
+    /// Pastes the files in the clipboard into `dir`.
+    ///
+    /// Copies get a new name if the name is taken. Cut files are moved, and
+    /// the clipboard is emptied.
+    pub fn paste(&self, dir: &Path) {
+        let Some(clipboard) = self.clipboard.get_untracked() else {
+            return;
+        };
+
+        for from in clipboard.paths {
+            let Some(file_name) = from.file_name() else {
+                continue;
+            };
+            // cannot paste a directory inside of itself
+            if dir.starts_with(&from) {
+                notify::warn(
+                    tr("Cannot paste a directory inside of itself"),
+                    from.display().to_string(),
+                    Vec::new(),
+                );
+                continue;
+            }
+
+            let to = dir.join(file_name);
+            if !clipboard.cut {
+                self.copy(from, copy_path(&to));
+            } else if to != from {
+                self.rename(from, to);
+            }
+        }
+
+        if clipboard.cut {
+            self.clipboard.set(None);
+        }
+    }
+
+    /// Undoes the last file operation.
+    pub fn undo(&self) {
+        let result = self
+            .op_log
+            .try_update(|log| log.undo())
+            .and_then(Result::transpose);
+        self.finish(result, "Failed to undo");
+    }
+
+    /// Redoes the last undone file operation.
+    pub fn redo(&self) {
+        let result = self
+            .op_log
+            .try_update(|log| log.redo())
+            .and_then(Result::transpose);
+        self.finish(result, "Failed to redo");
+    }
+
+    /// Like [`ExplorerState::finish`], but asks to confirm `op` if it failed
+    /// because its path is protected.
+    fn finish_guarded(&self, result: Option<Result<FileOp, Error>>, op: FileOp, failed: &str) {
+        match result {
+            Some(Err(Error::Protected(_))) => self.confirming.set(Some(op)),
+            result => self.finish(result, failed),
+        }
+    }
+
+    /// Applies the result of an operation to every pane, or notifies the
+    /// user with the `failed` message if it failed.
+    fn finish(&self, result: Option<Result<FileOp, Error>>, failed: &str) {
+        match result {
+            Some(Ok(op)) => self.panes.with_untracked(|panes| {
+                for pane in panes {
+                    pane.tree.update(|tree| {
+                        op.apply(tree);
+                        badges::recount(tree);
+                    });
+                }
+            }),
+            Some(Err(err)) => notify::error(tr(failed), err.to_string(), Vec::new()),
+            None => (),
+        }
+    }
+}
+
+/// Loads the keymap, warning about plugin bindings it shadows.
+fn load_keymap() -> Keymap {
+    let keymap = Keymap::load();
+    crate::plugin::warn_shadowed(&keymap);
+    keymap
+}
+
+/// Opens the file at `path` in `tabs`. See [`Pane::open_tab`].
+fn open_tab(tabs: &mut Vec<PreviewTab>, path: &Path, pinned: bool) {
+    if let Some(tab) = tabs.iter_mut().find(|tab| tab.path == path) {
+        tab.pinned |= pinned;
+        return;
+    }
+
+    let tab = PreviewTab {
+        path: path.to_owned(),
+        pinned,
+    };
+    match tabs.iter_mut().find(|tab| !tab.pinned) {
+        Some(preview) if !pinned => *preview = tab,
+        _ => tabs.push(tab),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        assert_eq!(history.back(), None);
+
+        history.visit(PathBuf::from("/var/MAPINFO"));
+        history.visit(PathBuf::from("/var/DECORATE"));
+        history.visit(PathBuf::from("/var/DECORATE"));
+        history.visit(PathBuf::from("/var/SNDINFO"));
+        assert_eq!(history.back(), Some(Path::new("/var/DECORATE")));
+        assert_eq!(history.back(), Some(Path::new("/var/MAPINFO")));
+        assert_eq!(history.back(), None);
+        assert_eq!(history.forward(), Some(Path::new("/var/DECORATE")));
+
+        // visiting drops the nodes ahead
+        history.visit(PathBuf::from("/var/TEXTURES"));
+        assert_eq!(history.forward(), None);
+        assert_eq!(history.back(), Some(Path::new("/var/DECORATE")));
+    }
+
+    #[test]
+    fn test_open_tab() {
+        let tab = |path: &str, pinned| PreviewTab {
+            path: PathBuf::from(path),
+            pinned,
+        };
+
+        let mut tabs = Vec::new();
+        open_tab(&mut tabs, Path::new("/var/MAPINFO"), false);
+        open_tab(&mut tabs, Path::new("/var/DECORATE"), false);
+        assert_eq!(tabs, [tab("/var/DECORATE", false)]);
+
+        open_tab(&mut tabs, Path::new("/var/DECORATE"), true);
+        open_tab(&mut tabs, Path::new("/var/MAPINFO"), false);
+        open_tab(&mut tabs, Path::new("/var/ZMAPINFO"), false);
+        open_tab(&mut tabs, Path::new("/var/SNDINFO"), true);
+        assert_eq!(
+            tabs,
+            [
+                tab("/var/DECORATE", true),
+                tab("/var/ZMAPINFO", false),
+                tab("/var/SNDINFO", true),
+            ]
+        );
+    }
+}
@@ -1,25 +1,1533 @@
 //! The actual Floem views associated with the file explorer.
 
+use floem::action::exec_after;
+use floem::event::{Event, EventListener, EventPropagation};
+use floem::keyboard::{Key, NamedKey};
+use floem::menu::{Menu, MenuItem};
 use floem::prelude::*;
+use floem::Clipboard;
 
-use super::data::Tree;
-use super::list::TreeView;
-
-/// The file explorer view.
-pub fn file_explorer_view(tree: RwSignal<Tree>) -> impl IntoView {
-    scroll(
-        virtual_list(
-            VirtualDirection::Vertical,
-            VirtualItemSize::Fixed(Box::new(|| 20.0)),
-            move || TreeView::new(tree.get()),
-            move |item| item.node.path().to_owned(),
-            move |item| {
-                let padding = item.level as f32 * 12.0;
-                label(move || item.file_name().to_owned())
-                    .style(move |s| s.height(20.0).padding_left(padding))
+use floem::kurbo::{Point, Rect};
+use floem::reactive::{create_effect, create_memo, untrack};
+
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::keymap::{Command, KeyChord};
+use crate::locale::{tr, tr_args};
+use crate::notify;
+use crate::plugin::{self, Invocation};
+use crate::system_open;
+
+use super::archive::{is_archive, is_member, ArchiveMember};
+use super::badges;
+use super::data::{Tree, TreeIndex};
+use super::diff::diff_view;
+use super::fs::LocalFs;
+use super::list::{
+    find_by_pattern, recent_files, Filter, Hidden, NodeView, ScrollBehavior, SortMode, TreeView,
+};
+use super::ops::{format_path, FileOp};
+use super::preview::preview_view_with;
+use super::state::{ExplorerState, FileClipboard, Pane};
+
+/// The height of a row in the explorer.
+const ROW_HEIGHT: f64 = 20.0;
+/// How far each level of the tree is indented.
+const INDENT: f32 = 12.0;
+/// The number of files listed in the "Recent" section.
+const RECENT_COUNT: usize = 8;
+/// The most matches listed by Find by Pattern.
+const MAX_FIND_RESULTS: usize = 200;
+/// How long a smooth scroll takes.
+const SMOOTH_SCROLL: Duration = Duration::from_millis(150);
+/// The time between the steps of a smooth scroll.
+const SMOOTH_SCROLL_STEP: Duration = Duration::from_millis(16);
+
+/// The file explorer view for a single pane.
+///
+/// Shows a filter box above the tree, and a "scanning…" indicator while the
+/// pane is being scanned.
+pub fn file_explorer_view(state: ExplorerState, pane: Pane) -> impl IntoView {
+    let Pane {
+        tree,
+        scanning,
+        selected,
+        preview,
+        revealed,
+        scroll_target,
+        renaming,
+        details,
+        sort,
+        flat,
+        diff,
+        finding,
+        ..
+    } = pane;
+
+    // follow the settings, until they are changed from the header
+    let hidden = create_rw_signal(None::<Rc<Hidden>>);
+    create_effect(move |_| {
+        state.settings.with(|settings| {
+            sort.set(settings.sort);
+            details.set(settings.details);
+            flat.set(settings.flat);
+
+            let globs = settings.hidden_globs();
+            hidden.set((!globs.is_empty()).then(|| Rc::new(Hidden::new(globs))));
+        })
+    });
+
+    let query = create_rw_signal(String::new());
+    let filter = create_rw_signal(None::<Rc<Filter>>);
+    create_effect(move |was_empty| {
+        let new_filter = query.with(|query| {
+            if query.is_empty() {
+                None
+            } else {
+                Some(Rc::new(tree.with(|tree| Filter::new(tree, query))))
+            }
+        });
+        let is_empty = new_filter.is_none();
+        filter.set(new_filter);
+
+        // reveal the selected match in the tree when the filter is cleared
+        if is_empty && was_empty == Some(false) {
+            if let Some(path) = selected.get_untracked() {
+                pane.reveal(path);
+            }
+        }
+        is_empty
+    });
+
+    let filter_box = (
+        text_input(query)
+            .placeholder(tr("Filter"))
+            .style(|s| s.flex_grow(1.0).min_width(0.0)),
+        label(move || {
+            filter.with(|filter| {
+                filter
+                    .as_ref()
+                    .map(|f| tr_args("{count} found", &[("count", &f.match_count())]))
+                    .unwrap_or_default()
+            })
+        })
+        .style(move |s| {
+            s.padding_left(4.0)
+                .color(Color::GRAY)
+                .apply_if(query.with(String::is_empty), |s| s.hide())
+        }),
+        button(label(|| tr("Collapse")))
+            .action(move || run_command(state, pane, Command::CollapseAll)),
+        button(label(|| tr("Expand"))).action(move || run_command(state, pane, Command::ExpandAll)),
+        button(label(|| tr("Refresh"))).action(move || run_command(state, pane, Command::Refresh)),
+        button(label(|| tr("Sort"))).popout_menu(move || {
+            SortMode::ALL.into_iter().fold(Menu::new(""), |menu, mode| {
+                let name = if sort.get_untracked() == mode {
+                    format!("✓ {}", tr(mode.name()))
+                } else {
+                    tr(mode.name())
+                };
+                menu.entry(MenuItem::new(name).action(move || sort.set(mode)))
+            })
+        }),
+        button(label(
+            move || if flat.get() { tr("Tree") } else { tr("Flat") },
+        ))
+        .action(move || run_command(state, pane, Command::ToggleFlat)),
+        button(label(|| tr("Details")))
+            .action(move || details.update(|details| *details = !*details)),
+    )
+        .h_stack()
+        .style(|s| s.items_center().gap(2.0).padding(2.0));
+
+    let scanning_label = label(|| tr("scanning…")).style(move |s| {
+        s.height(20.0)
+            .padding_horiz(4.0)
+            .apply_if(!scanning.get(), |s| s.hide())
+    });
+
+    // the rows of the list, with everything that changes them, built from
+    // the rows last listed. `listed` is only read untracked, so setting it
+    // does not rebuild anything
+    let listed = create_rw_signal(None::<TreeView>);
+    let tree_view = move || {
+        let view = TreeView::new(tree)
+            .with_filter(filter.get())
+            .with_sort(sort.get())
+            .with_flat(flat.get())
+            .with_hidden(hidden.get());
+        let view = listed.with_untracked(|last| match last {
+            Some(last) => view.reusing(last),
+            None => view,
+        });
+        listed.set(Some(view.clone()));
+        view
+    };
+
+    let list = virtual_list(
+        VirtualDirection::Vertical,
+        VirtualItemSize::Fixed(Box::new(|| ROW_HEIGHT)),
+        tree_view,
+        move |item| item.node.path().to_owned(),
+        move |item| row_view(state, pane, item),
+    )
+    .on_select(move |index| {
+        let path = index.and_then(|index| {
+            untrack(tree_view)
+                .row(index)
+                .map(|row| row.node.path().to_owned())
+        });
+        // the selection is restored after the tree changes, which is not a
+        // move
+        if selected.with_untracked(|selected| *selected != path) {
+            // revealed nodes are already being scrolled to
+            let target = scroll_target.with_untracked(|target| target.map(|(ix, _)| ix));
+            let ix = path
+                .as_ref()
+                .and_then(|path| tree.with_untracked(|tree| tree.find(path)))
+                .filter(|ix| target != Some(*ix));
+            if let Some(ix) = ix {
+                pane.scroll_to(ix, ScrollBehavior::Instant);
+            }
+            selected.set(path);
+            // moving the selection dismisses the preview
+            preview.set(None);
+        }
+    })
+    .on_event(EventListener::KeyDown, move |e| {
+        let Event::KeyDown(e) = e else {
+            return EventPropagation::Continue;
+        };
+        // keys typed while renaming belong to the rename input
+        if renaming.with_untracked(Option::is_some) {
+            return EventPropagation::Continue;
+        }
+
+        if let Some(command) = state.keymap.with_untracked(|keymap| keymap.lookup(e)) {
+            run_command(state, pane, command);
+            return EventPropagation::Stop;
+        }
+        match plugin::lookup(&KeyChord::from_event(e)) {
+            Some(id) => {
+                run_plugin_command(pane, &id, selected.get_untracked());
+                EventPropagation::Stop
+            }
+            None => EventPropagation::Continue,
+        }
+    })
+    .style(|s| s.flex_col().width_full());
+
+    // select and scroll to revealed nodes
+    let selection = list.selection();
+    create_effect(move |_| {
+        let Some(path) = revealed.get() else {
+            return;
+        };
+        untrack(|| {
+            let Some(ix) = tree.with(|tree| tree.find(&path)) else {
+                return;
+            };
+            if let Some(index) = tree_view().position(ix) {
+                pane.scroll_to(ix, ScrollBehavior::Smooth);
+                selection.set(Some(index));
+            }
+        });
+    });
+
+    // scroll to the target of `Pane::scroll_to`
+    let viewport = create_rw_signal(Rect::ZERO);
+    let scroll_to = create_rw_signal(None::<Point>);
+    // bumped to stop a smooth scroll
+    let scrolls = create_rw_signal(0u64);
+    create_effect(move |_| {
+        let Some((ix, behavior)) = scroll_target.get() else {
+            return;
+        };
+        untrack(|| {
+            let rect = viewport.get();
+            let Some(y) = tree_view().scroll_offset(ix, rect.y0, rect.height(), ROW_HEIGHT) else {
+                return;
+            };
+            scrolls.update(|scrolls| *scrolls += 1);
+            match behavior {
+                ScrollBehavior::Instant => scroll_to.set(Some(Point::new(rect.x0, y))),
+                ScrollBehavior::Smooth => smooth_scroll(scroll_to, scrolls, rect.origin(), y),
+            }
+        });
+    });
+
+    // keep the rows in view and the selection in place when the rows change:
+    // `anchor` is the path of the top row of `shown`, the rows last shown,
+    // and is scrolled back to the top after a change
+    let shown = create_rw_signal(None::<Rc<TreeView>>);
+    let anchor = create_rw_signal(None::<PathBuf>);
+    let top_path = move |view: &TreeView| {
+        let top = viewport.with_untracked(|rect| rect.y0 / ROW_HEIGHT) as usize;
+        view.row(top).map(|row| row.node.path)
+    };
+    create_effect(move |_| {
+        let view = tree_view();
+
+        untrack(|| {
+            let rect = viewport.get();
+            let top = (rect.y0 / ROW_HEIGHT) as usize;
+            let index = anchor.with(|path| view.find(path.as_deref()?));
+            match index {
+                Some(index) if index != top => {
+                    let y = rect.y0 + (index as f64 - top as f64) * ROW_HEIGHT;
+                    scroll_to.set(Some(Point::new(rect.x0, y)));
+                }
+                // the anchor is set again once scrolled
+                _ => anchor.set(top_path(&view)),
+            }
+
+            let index = selected.with(|path| view.find(path.as_deref()?));
+            if index != selection.get() {
+                selection.set(index);
+            }
+        });
+
+        shown.set(Some(Rc::new(view)));
+    });
+
+    let list = scroll(list)
+        .scroll_to(move || scroll_to.get())
+        .on_scroll(move |rect| {
+            viewport.set(rect);
+            let path = shown.with_untracked(|view| {
+                let view = view.as_ref().filter(|view| view.is_current())?;
+                top_path(view)
+            });
+            anchor.set(path);
+        })
+        .style(|s| s.size_full());
+
+    let preview_layer = (
+        tabs_view(pane),
+        dyn_container(
+            move || preview.get(),
+            move |path| match path {
+                Some(path) => {
+                    // members of archives are read from the open archive
+                    let fs = pane.tree.with_untracked(|tree| {
+                        let ix = tree.find(&path)?;
+                        tree.get(ix).map(|node| node.fs.clone())
+                    });
+                    preview_view_with(fs.unwrap_or_else(LocalFs::shared), path).into_any()
+                }
+                None => empty().into_any(),
             },
         )
-        .style(|s| s.flex_col().width_full()),
+        .style(|s| s.width_full().flex_grow(1.0).min_height(0.0)),
+    )
+        .v_stack()
+        .style(move |s| {
+            s.absolute()
+                .inset(0.0)
+                .apply_if(preview.with(Option::is_none), |s| s.hide())
+        });
+
+    let diff_layer = dyn_container(
+        move || diff.get(),
+        |diff| match diff {
+            Some((old, new)) => diff_view(old, new).into_any(),
+            None => empty().into_any(),
+        },
+    )
+    .style(move |s| {
+        s.absolute()
+            .inset(0.0)
+            .apply_if(diff.with(Option::is_none), |s| s.hide())
+    });
+
+    let find_layer = dyn_container(
+        move || finding.get(),
+        move |finding| {
+            if finding {
+                find_view(pane).into_any()
+            } else {
+                empty().into_any()
+            }
+        },
+    )
+    .style(move |s| {
+        s.absolute()
+            .inset(0.0)
+            .apply_if(!finding.get(), |s| s.hide())
+    });
+
+    let body = (list, preview_layer, diff_layer, find_layer)
+        .v_stack()
+        .style(|s| s.width_full().flex_grow(1.0).min_height(0.0));
+
+    (
+        filter_box,
+        scanning_label,
+        favorites_view(state, pane),
+        recent_view(pane),
+        body,
+    )
+        .v_stack()
+        // the back and forward buttons of the mouse
+        .on_event(EventListener::PointerDown, move |e| match e {
+            Event::PointerDown(e) if e.button.is_x1() => {
+                run_command(state, pane, Command::NavigateBack);
+                EventPropagation::Stop
+            }
+            Event::PointerDown(e) if e.button.is_x2() => {
+                run_command(state, pane, Command::NavigateForward);
+                EventPropagation::Stop
+            }
+            _ => EventPropagation::Continue,
+        })
+        .style(move |s| {
+            s.width(if details.get() { 380.0 } else { 200.0 })
+                .height(100.pct())
+                .border(1.0)
+        })
+}
+
+/// The tabs of the files open in the preview, above it.
+///
+/// The preview tab is in italics. Double clicking it keeps it open when
+/// another file is previewed.
+fn tabs_view(pane: Pane) -> impl IntoView {
+    let Pane { tabs, preview, .. } = pane;
+
+    dyn_stack(
+        move || tabs.get(),
+        |tab| tab.path.clone(),
+        move |tab| {
+            let name = tab
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let path = tab.path.clone();
+            let is_shown = {
+                let path = path.clone();
+                move || preview.with(|preview| preview.as_ref() == Some(&path))
+            };
+            // tabs are keyed by path, so follow the tab for when it is pinned
+            let is_pinned = {
+                let path = path.clone();
+                move || tabs.with(|tabs| tabs.iter().any(|tab| tab.path == path && tab.pinned))
+            };
+            let close_path = path.clone();
+            let pin_path = path.clone();
+
+            (
+                label(move || name.clone()).style(move |s| {
+                    s.apply_if(!is_pinned(), |s| s.font_style(floem::text::Style::Italic))
+                }),
+                label(|| "×")
+                    .on_click_stop(move |_| pane.close_tab(&close_path))
+                    .style(|s| s.padding_horiz(4.0).color(Color::GRAY)),
+            )
+                .h_stack()
+                .on_click_stop(move |_| preview.set(Some(path.clone())))
+                .on_double_click_stop(move |_| pane.pin_tab(&pin_path))
+                .style(move |s| {
+                    s.height(ROW_HEIGHT)
+                        .padding_left(6.0)
+                        .items_center()
+                        .border_right(1.0)
+                        .apply_if(is_shown(), |s| s.background(Color::LIGHT_GRAY))
+                })
+        },
+    )
+    .style(|s| s.width_full().border_bottom(1.0))
+}
+
+/// Scrolls from `from` down or up to `y` over [`SMOOTH_SCROLL`], slowing
+/// down towards the end. Stops early if `scrolls` changes, as another scroll
+/// has started.
+fn smooth_scroll(scroll_to: RwSignal<Option<Point>>, scrolls: RwSignal<u64>, from: Point, y: f64) {
+    let scroll = scrolls.get_untracked();
+    let start = Instant::now();
+
+    fn step(
+        scroll_to: RwSignal<Option<Point>>,
+        scrolls: RwSignal<u64>,
+        scroll: u64,
+        start: Instant,
+        from: Point,
+        y: f64,
+    ) {
+        if scrolls.get_untracked() != scroll {
+            return;
+        }
+
+        let t = (start.elapsed().as_secs_f64() / SMOOTH_SCROLL.as_secs_f64()).min(1.0);
+        let eased = 1.0 - (1.0 - t).powi(3);
+        scroll_to.set(Some(Point::new(from.x, from.y + (y - from.y) * eased)));
+        if t < 1.0 {
+            exec_after(SMOOTH_SCROLL_STEP, move |_| {
+                step(scroll_to, scrolls, scroll, start, from, y)
+            });
+        }
+    }
+    step(scroll_to, scrolls, scroll, start, from, y);
+}
+
+/// The favorites, pinned above the tree. Hidden while there are none.
+///
+/// Clicking a favorite reveals it, and previews it if it is a file.
+/// Favorites are reordered by dragging them onto each other.
+fn favorites_view(state: ExplorerState, pane: Pane) -> impl IntoView {
+    let favorites = state.favorites;
+    let paths = move || {
+        favorites.with(|favorites| {
+            favorites
+                .paths()
+                .iter()
+                .cloned()
+                .enumerate()
+                .collect::<Vec<_>>()
+        })
+    };
+    let dragging = create_rw_signal(None::<PathBuf>);
+
+    let header =
+        label(|| tr("Favorites")).style(|s| s.height(ROW_HEIGHT).padding_horiz(4.0).items_center());
+
+    let rows = dyn_stack(
+        paths,
+        |(index, path)| (*index, path.clone()),
+        move |(index, path)| {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let drag_path = path.clone();
+            let remove_path = path.clone();
+            label(move || name.clone())
+                .on_click_stop(move |_| {
+                    pane.jump_to(path.clone());
+                    if path.is_file() {
+                        pane.open_tab(path.clone(), false);
+                    }
+                })
+                .draggable()
+                .on_event_cont(EventListener::DragStart, move |_| {
+                    dragging.set(Some(drag_path.clone()))
+                })
+                .on_event_cont(EventListener::DragEnd, move |_| dragging.set(None))
+                .on_event_stop(EventListener::Drop, move |_| {
+                    if let Some(from) = dragging.get_untracked() {
+                        favorites.update(|favorites| favorites.move_to(&from, index));
+                    }
+                })
+                .context_menu(move || {
+                    let path = remove_path.clone();
+                    Menu::new("").entry(
+                        MenuItem::new(tr("Remove from Favorites"))
+                            .action(move || favorites.update(|favorites| favorites.toggle(&path))),
+                    )
+                })
+                .style(|s| {
+                    s.height(ROW_HEIGHT)
+                        .padding_left(16.0)
+                        .items_center()
+                        .hover(|s| s.background(Color::LIGHT_GRAY))
+                })
+        },
+    )
+    .style(|s| s.flex_col());
+
+    (header, rows).v_stack().style(move |s| {
+        s.width_full().border_bottom(1.0).apply_if(
+            favorites.with(|favorites| favorites.paths().is_empty()),
+            |s| s.hide(),
+        )
+    })
+}
+
+/// A collapsible list of the most recently modified files in a pane.
+///
+/// Clicking a file reveals and previews it.
+fn recent_view(pane: Pane) -> impl IntoView {
+    let open = create_rw_signal(false);
+    // only read file times while the section is open
+    let recent = create_memo(move |_| {
+        if !open.get() {
+            return Vec::new();
+        }
+        pane.tree.with(|tree| {
+            recent_files(tree, RECENT_COUNT)
+                .into_iter()
+                .filter_map(|ix| Some(tree.get(ix)?.path().to_owned()))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let header = label(move || {
+        if open.get() {
+            format!("▾ {}", tr("Recent"))
+        } else {
+            format!("▸ {}", tr("Recent"))
+        }
+    })
+    .on_click_stop(move |_| open.update(|open| *open = !*open))
+    .style(|s| s.height(ROW_HEIGHT).padding_horiz(4.0).items_center());
+
+    let files = dyn_stack(
+        move || recent.get(),
+        |path| path.clone(),
+        move |path| {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            label(move || name.clone())
+                .on_click_stop(move |_| {
+                    pane.jump_to(path.clone());
+                    pane.open_tab(path.clone(), false);
+                })
+                .style(|s| {
+                    s.height(ROW_HEIGHT)
+                        .padding_left(16.0)
+                        .items_center()
+                        .hover(|s| s.background(Color::LIGHT_GRAY))
+                })
+        },
     )
-    .style(|s| s.width(200.0).height(100.pct()).border(1.0))
+    .style(|s| s.flex_col());
+
+    (header, files)
+        .v_stack()
+        .style(|s| s.width_full().border_bottom(1.0))
+}
+
+/// Runs a command on a pane.
+fn run_command(state: ExplorerState, pane: Pane, command: Command) {
+    crate::logging::ran_command(command.id());
+    match command {
+        Command::TogglePreview => toggle_preview(pane),
+        Command::ClosePreview => {
+            pane.preview.set(None);
+            pane.diff.set(None);
+            pane.finding.set(false);
+        }
+        Command::Rename => pane.renaming.set(selected_editable(pane)),
+        Command::Delete => {
+            if let Some(path) = selected_editable(pane) {
+                state.delete(path);
+            }
+        }
+        Command::Undo => state.undo(),
+        Command::Redo => state.redo(),
+        Command::Expand | Command::Collapse => {
+            if let Some(path) = pane.selected.get_untracked() {
+                pane.set_open(&path, command == Command::Expand);
+            }
+        }
+        Command::CollapseAll => pane.tree.update(Tree::collapse_all),
+        Command::ExpandAll => pane.tree.update(|tree| tree.expand_to_depth(usize::MAX)),
+        Command::ToggleFlat => pane.flat.update(|flat| *flat = !*flat),
+        Command::Copy | Command::Cut => {
+            if let Some(path) = selected_editable(pane) {
+                state.clipboard.set(Some(FileClipboard {
+                    paths: vec![path],
+                    cut: command == Command::Cut,
+                }));
+            }
+        }
+        Command::Paste => {
+            if !selected_member(pane) {
+                state.paste(&paste_dir(pane));
+            }
+        }
+        Command::Duplicate => {
+            if let Some(path) = selected_editable(pane) {
+                state.duplicate(pane, path);
+            }
+        }
+        Command::NewFile => {
+            if !selected_member(pane) {
+                state.new_file(pane, &paste_dir(pane), None);
+            }
+        }
+        Command::Refresh => pane.refresh(),
+        Command::CopyPath | Command::CopyRelativePath => {
+            let template = if command == Command::CopyPath {
+                "{path}"
+            } else {
+                "{rel}"
+            };
+            if let Some(path) = pane.selected.get_untracked() {
+                copy_path_as(pane, template, &path);
+            }
+        }
+        Command::ToggleFavorite => {
+            if let Some(path) = selected_child(pane) {
+                state.favorites.update(|favorites| favorites.toggle(path));
+            }
+        }
+        Command::FindByPattern => pane.finding.update(|finding| *finding = !*finding),
+        Command::OpenLog => crate::workspace::open_log(),
+        Command::ReloadSettings => state.reload_settings(),
+        Command::NavigateBack => pane.go_back(),
+        Command::NavigateForward => pane.go_forward(),
+    }
+}
+
+/// The directory files are pasted or created in: the selected directory, or
+/// the directory of the selected file. Defaults to the root.
+fn paste_dir(pane: Pane) -> PathBuf {
+    pane.tree.with_untracked(|tree| {
+        pane.selected
+            .get_untracked()
+            .and_then(|path| tree.get(tree.find(&path)?))
+            .and_then(|node| {
+                if node.is_dir {
+                    Some(node.path().to_owned())
+                } else {
+                    node.path().parent().map(Path::to_owned)
+                }
+            })
+            .unwrap_or_else(|| tree.root().path().to_owned())
+    })
+}
+
+/// The path of the selected node, unless it is the root. The root cannot be
+/// renamed or deleted from inside the tree.
+fn selected_child(pane: Pane) -> Option<PathBuf> {
+    let root = pane
+        .tree
+        .with_untracked(|tree| tree.root().path().to_owned());
+    pane.selected.get_untracked().filter(|path| *path != root)
+}
+
+/// The selected node, unless it is the root or a member of an archive, which
+/// cannot be changed.
+fn selected_editable(pane: Pane) -> Option<PathBuf> {
+    selected_child(pane).filter(|path| {
+        pane.tree
+            .with_untracked(|tree| tree.find(path).is_none_or(|ix| !is_member(tree, ix)))
+    })
+}
+
+/// Checks if the selected node is a member of an archive.
+fn selected_member(pane: Pane) -> bool {
+    pane.tree.with_untracked(|tree| {
+        pane.selected
+            .with_untracked(|selected| selected.as_ref().and_then(|path| tree.find(path)))
+            .is_some_and(|ix| is_member(tree, ix))
+    })
+}
+
+/// Previews the selected file, or closes the preview if it is already open.
+fn toggle_preview(pane: Pane) {
+    if pane.preview.with_untracked(Option::is_some) {
+        pane.preview.set(None);
+        return;
+    }
+
+    let Some(path) = pane.selected.get_untracked() else {
+        return;
+    };
+    let is_file = pane.tree.with_untracked(|tree| {
+        tree.find(&path)
+            .and_then(|ix| tree.get(ix))
+            .is_some_and(|node| !node.is_dir)
+    });
+    if is_file {
+        pane.open_tab(path, false);
+    }
+}
+
+/// A single row in the explorer.
+fn row_view(state: ExplorerState, pane: Pane, item: NodeView) -> impl IntoView {
+    let level = item.level;
+    let path = item.node.path().to_owned();
+    // rows dropped onto a directory go inside it, otherwise beside the row
+    let drop_dir = if item.node.is_dir {
+        Some(path.clone())
+    } else {
+        path.parent().map(Path::to_owned)
+    };
+
+    let paste_dir = drop_dir.clone();
+
+    let readme = find_readme(pane.tree, item.ix);
+
+    // symlinks show where they point
+    let link_target = item
+        .node
+        .link_target
+        .as_ref()
+        .map(|target| format!("→ {}", target.display()));
+    let is_symlink = link_target.is_some();
+    let is_root = item.level == 0;
+    let is_file = !item.node.is_dir;
+    // members of archives are read-only, and not on disk
+    let is_member = item.node.data::<ArchiveMember>().is_some();
+    // rows are kept while their path is the same, so follow the tree. Reads
+    // metadata, so the lock is only shown with the details columns
+    let is_read_only = {
+        let path = path.clone();
+        move || {
+            pane.tree.with(|tree| {
+                tree.find(&path)
+                    .and_then(|ix| tree.get(ix))
+                    .is_some_and(|node| node.is_read_only())
+            })
+        }
+    };
+    let file_name = item.file_name().into_owned();
+    // only read metadata once the details columns are shown
+    let item = Rc::new(item);
+    let details = pane.details;
+    // directories count their children instead
+    let size = {
+        let item = item.clone();
+        move || {
+            if !details.get() {
+                String::new()
+            } else if item.node.is_dir {
+                let count = pane.tree.with(|tree| tree.children(item.ix).count());
+                tr_args("{count} items", &[("count", &count)])
+            } else {
+                item.size_text()
+            }
+        }
+    };
+    // directories sum up the badges of everything inside them
+    let badges = {
+        let path = path.clone();
+        move || {
+            pane.tree.with(|tree| {
+                tree.find(&path)
+                    .and_then(|ix| tree.get(ix))
+                    .map(badges::get)
+                    .unwrap_or_default()
+            })
+        }
+    };
+    let modified_badge = {
+        let badges = badges.clone();
+        move || match badges().modified {
+            0 => String::new(),
+            count => format!("● {}", count),
+        }
+    };
+    let problems_badge = move || match badges().problems {
+        0 => String::new(),
+        count => format!("⚠ {}", count),
+    };
+    let modified = move || {
+        if details.get() {
+            item.modified_text()
+        } else {
+            String::new()
+        }
+    };
+
+    // in flat mode, files show the directory they are in
+    let flat = pane.flat;
+    let dir = pane.tree.with_untracked(|tree| {
+        path.parent()
+            .and_then(|dir| dir.strip_prefix(tree.root().path()).ok())
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default()
+    });
+
+    // a guide line for every ancestor, lit up along the hovered or selected
+    // node's ancestors
+    let guides = (1..=level)
+        .rev()
+        .filter_map(|up| path.ancestors().nth(up).map(Path::to_owned))
+        .map(|ancestor| {
+            let is_active = move || {
+                let active = pane.hovered.get().or_else(|| pane.selected.get());
+                active.is_some_and(|active| active != ancestor && active.starts_with(&ancestor))
+            };
+            let line = empty().style(move |s| {
+                s.width(1.0).height_full().background(if is_active() {
+                    Color::GRAY
+                } else {
+                    Color::LIGHT_GRAY
+                })
+            });
+            container(line).style(|s| s.width(INDENT).height_full().padding_left(INDENT / 2.0))
+        })
+        .collect::<Vec<_>>()
+        .h_stack()
+        .style(|s| s.height_full());
+
+    // directories open and close from their chevron, or by clicking the row
+    let is_dir = !is_file && !is_root;
+    let chevron = {
+        let path = path.clone();
+        move || {
+            let is_open = pane.tree.with(|tree| {
+                tree.find(&path)
+                    .and_then(|ix| tree.get(ix))
+                    .is_some_and(|node| node.is_open)
+            });
+            match (is_dir, is_open) {
+                (false, _) => "",
+                (true, true) => "▾",
+                (true, false) => "▸",
+            }
+        }
+    };
+
+    let name = dyn_container(
+        {
+            let path = path.clone();
+            move || {
+                pane.renaming
+                    .with(|renaming| renaming.as_ref() == Some(&path))
+            }
+        },
+        {
+            let path = path.clone();
+            move |is_renaming| {
+                if is_renaming {
+                    rename_input(state, pane, path.clone()).into_any()
+                } else {
+                    let file_name = file_name.clone();
+                    label(move || file_name.clone()).into_any()
+                }
+            }
+        },
+    );
+
+    (
+        guides,
+        label(chevron).style(|s| s.width(INDENT).color(Color::GRAY)),
+        name,
+        label(move || dir.clone()).style(move |s| {
+            s.padding_left(6.0)
+                .color(Color::GRAY)
+                .apply_if(!flat.get(), |s| s.hide())
+        }),
+        label(move || link_target.clone().unwrap_or_default()).style(move |s| {
+            s.padding_left(6.0)
+                .color(Color::GRAY)
+                .apply_if(!is_symlink, |s| s.hide())
+        }),
+        label(|| "🔒").style({
+            let is_read_only = is_read_only.clone();
+            move |s| {
+                s.padding_left(6.0)
+                    .apply_if(!details.get() || !is_read_only(), |s| s.hide())
+            }
+        }),
+        label(modified_badge).style(|s| s.padding_left(6.0).color(Color::rgb8(204, 136, 0))),
+        label(problems_badge).style(|s| s.padding_left(6.0).color(Color::RED)),
+        empty().style(|s| s.flex_grow(1.0)),
+        label(size).style(move |s| {
+            s.width(70.0)
+                .color(Color::GRAY)
+                .apply_if(!details.get(), |s| s.hide())
+        }),
+        label(modified).style(move |s| {
+            s.width(110.0)
+                .color(Color::GRAY)
+                .apply_if(!details.get(), |s| s.hide())
+        }),
+    )
+        .h_stack()
+        .style(move |s| s.height(ROW_HEIGHT).width_full().items_center())
+        .on_event_cont(EventListener::PointerEnter, {
+            let path = path.clone();
+            move |_| pane.hovered.set(Some(path.clone()))
+        })
+        .on_event_cont(EventListener::PointerLeave, {
+            let path = path.clone();
+            move |_| {
+                if pane
+                    .hovered
+                    .with_untracked(|hovered| hovered.as_ref() == Some(&path))
+                {
+                    pane.hovered.set(None);
+                }
+            }
+        })
+        .on_click_cont({
+            let path = path.clone();
+            move |_| {
+                pane.history.update(|history| history.visit(path.clone()));
+                if is_dir {
+                    pane.toggle_open(&path);
+                }
+                let preview_on_click = state
+                    .settings
+                    .with_untracked(|settings| settings.preview_on_click);
+                if is_file && preview_on_click {
+                    pane.open_tab(path.clone(), false);
+                }
+            }
+        })
+        .on_double_click_cont({
+            let path = path.clone();
+            move |_| {
+                if is_file {
+                    pane.open_tab(path.clone(), true);
+                }
+            }
+        })
+        .draggable()
+        .on_event_cont(EventListener::DragStart, {
+            let path = path.clone();
+            move |_| {
+                if !is_member {
+                    state.dragging.set(Some(path.clone()));
+                }
+            }
+        })
+        .on_event_cont(EventListener::DragEnd, move |_| state.dragging.set(None))
+        .on_event_stop(EventListener::Drop, move |e| {
+            let (Some(from), Some(dir)) = (state.dragging.get_untracked(), drop_dir.as_ref())
+            else {
+                return;
+            };
+            if is_member {
+                return;
+            }
+            let Some(file_name) = from.file_name() else {
+                return;
+            };
+            // cannot move a directory inside of itself
+            if dir.starts_with(&from) || from.parent() == Some(dir.as_path()) {
+                return;
+            }
+
+            let to = dir.join(file_name);
+            match e {
+                Event::PointerUp(e) if e.modifiers.control() => state.copy(from, to),
+                _ => state.rename(from, to),
+            }
+        })
+        .context_menu(move || {
+            let path = path.clone();
+            let rename_path = path.clone();
+            let copy_path = path.clone();
+            let cut_path = path.clone();
+            let paste_dir = paste_dir.clone();
+            let duplicate_path = path.clone();
+            let plugin_path = path.clone();
+            let mut menu = Menu::new("");
+            if let Some(readme) = readme.clone() {
+                menu = menu
+                    .entry(
+                        MenuItem::new(tr("View README"))
+                            .action(move || pane.open_tab(readme.clone(), false)),
+                    )
+                    .separator();
+            }
+            if is_file {
+                let compare_path = path.clone();
+                let new = path.clone();
+                let old = state.compare_with.get_untracked();
+                let can_compare = old.as_ref().is_some_and(|old| *old != path);
+                menu = menu
+                    .entry(
+                        MenuItem::new(tr("Select for Compare"))
+                            .action(move || state.compare_with.set(Some(compare_path.clone()))),
+                    )
+                    .entry(
+                        MenuItem::new(tr("Compare with Selected"))
+                            .enabled(can_compare)
+                            .action(move || {
+                                if let Some(old) = old.clone() {
+                                    pane.diff.set(Some((old, new.clone())));
+                                }
+                            }),
+                    )
+                    .separator();
+            }
+            if !is_root {
+                let favorite_path = path.clone();
+                let is_favorite = state
+                    .favorites
+                    .with_untracked(|favorites| favorites.contains(&path));
+                menu = menu.entry(
+                    MenuItem::new(if is_favorite {
+                        tr("Remove from Favorites")
+                    } else {
+                        tr("Add to Favorites")
+                    })
+                    .action(move || {
+                        state
+                            .favorites
+                            .update(|favorites| favorites.toggle(favorite_path.clone()))
+                    }),
+                );
+            }
+            menu = menu.entry(copy_path_menu(state, pane, path.clone()));
+            if !is_member {
+                menu = menu.entry(open_with_menu(state, path.clone())).entry(
+                    MenuItem::new(tr("Reveal in File Manager")).action({
+                        let path = path.clone();
+                        move || {
+                            if let Err(err) = system_open::reveal(&path) {
+                                let body = format!("{}: {}", path.display(), err);
+                                notify::error(tr("Failed to reveal"), body, Vec::new());
+                            }
+                        }
+                    }),
+                );
+            }
+            menu = menu.separator();
+            if is_file && !is_member && is_archive(&path) {
+                let archive_path = path.clone();
+                menu = menu.entry(
+                    MenuItem::new(tr("Browse Archive"))
+                        .action(move || state.browse_archive(&archive_path)),
+                );
+            }
+            if !is_file && !is_member {
+                let window_path = path.clone();
+                menu = menu.entry(
+                    MenuItem::new(tr("Open in New Window"))
+                        .action(move || crate::workspace::open_window(window_path.clone())),
+                );
+            }
+            if let Some(dir) = paste_dir.clone().filter(|_| !is_member) {
+                menu = menu.entry(new_file_menu(state, pane, dir));
+            }
+            let menu = menu
+                .entry(
+                    MenuItem::new(tr("Duplicate"))
+                        .enabled(!is_root && !is_member)
+                        .action(move || state.duplicate(pane, duplicate_path.clone())),
+                )
+                .entry(
+                    MenuItem::new(tr("Cut"))
+                        .enabled(!is_root && !is_member)
+                        .action(move || {
+                            state.clipboard.set(Some(FileClipboard {
+                                paths: vec![cut_path.clone()],
+                                cut: true,
+                            }))
+                        }),
+                )
+                .entry(
+                    MenuItem::new(tr("Copy"))
+                        .enabled(!is_root && !is_member)
+                        .action(move || {
+                            state.clipboard.set(Some(FileClipboard {
+                                paths: vec![copy_path.clone()],
+                                cut: false,
+                            }))
+                        }),
+                )
+                .entry(
+                    MenuItem::new(tr("Paste"))
+                        .enabled(!is_member && state.clipboard.with_untracked(Option::is_some))
+                        .action(move || {
+                            if let Some(dir) = &paste_dir {
+                                state.paste(dir);
+                            }
+                        }),
+                )
+                .separator()
+                .entry(
+                    MenuItem::new(tr("Rename"))
+                        .enabled(!is_root && !is_member)
+                        .action(move || pane.renaming.set(Some(rename_path.clone()))),
+                )
+                .entry(
+                    MenuItem::new(tr("Delete"))
+                        .enabled(!is_root && !is_member)
+                        .action({
+                            let path = path.clone();
+                            move || state.delete(&path)
+                        }),
+                )
+                .entry(
+                    MenuItem::new(tr("Make Writable"))
+                        .enabled(!is_member && untrack(&is_read_only))
+                        .action(move || state.make_writable(&path)),
+                )
+                .separator()
+                .entry(
+                    MenuItem::new(tr("Undo last file operation"))
+                        .enabled(state.op_log.with_untracked(|log| log.can_undo()))
+                        .action(move || state.undo()),
+                )
+                .entry(
+                    MenuItem::new(tr("Redo file operation"))
+                        .enabled(state.op_log.with_untracked(|log| log.can_redo()))
+                        .action(move || state.redo()),
+                );
+            match plugin_menu(pane, plugin_path.clone()) {
+                Some(plugins) => menu.separator().entry(plugins),
+                None => menu,
+            }
+        })
+}
+
+/// A submenu running the plugin commands offered in the context menu on
+/// `path`, or `None` if no plugin offers any.
+fn plugin_menu(pane: Pane, path: PathBuf) -> Option<Menu> {
+    let commands = plugin::commands()
+        .into_iter()
+        .filter(|command| command.context_menu)
+        .collect::<Vec<_>>();
+    if commands.is_empty() {
+        return None;
+    }
+
+    let menu = commands
+        .into_iter()
+        .fold(Menu::new(tr("Plugins")), |menu, command| {
+            let path = path.clone();
+            menu.entry(
+                MenuItem::new(tr(&command.title))
+                    .action(move || run_plugin_command(pane, &command.id, Some(path.clone()))),
+            )
+        });
+    Some(menu)
+}
+
+/// Runs the plugin command `id` on `path`, in a pane.
+fn run_plugin_command(pane: Pane, id: &str, path: Option<PathBuf>) {
+    let root = pane
+        .tree
+        .with_untracked(|tree| tree.root().path().to_owned());
+    plugin::run(id, &Invocation { root, path });
+}
+
+/// A submenu copying `path` to the system clipboard, as is, relative to the
+/// root, or in one of the formats in the settings.
+fn copy_path_menu(state: ExplorerState, pane: Pane, path: PathBuf) -> Menu {
+    let copy = |template: String| {
+        let path = path.clone();
+        move || copy_path_as(pane, &template, &path)
+    };
+
+    let mut menu = Menu::new(tr("Copy Path"))
+        .entry(MenuItem::new(tr("Copy Path")).action(copy("{path}".to_owned())))
+        .entry(MenuItem::new(tr("Copy Relative Path")).action(copy("{rel}".to_owned())));
+    let templates = state
+        .settings
+        .with_untracked(|settings| settings.path_templates.clone());
+    if !templates.is_empty() {
+        menu = menu.separator();
+    }
+    for template in templates {
+        menu = menu.entry(MenuItem::new(template.clone()).action(copy(template)));
+    }
+    menu
+}
+
+/// Copies `path` to the system clipboard, formatted with `template`.
+fn copy_path_as(pane: Pane, template: &str, path: &Path) {
+    let root = pane
+        .tree
+        .with_untracked(|tree| tree.root().path().to_owned());
+    if let Err(err) = Clipboard::set_contents(format_path(template, &root, path)) {
+        notify::error(tr("Failed to copy path"), format!("{:?}", err), Vec::new());
+    }
+}
+
+/// Finds files in a pane by a glob pattern, like `src/**/*.rs` or `*test*`.
+///
+/// Clicking a match reveals it in the tree. "Open All" opens every listed
+/// match with the default application of the system.
+fn find_view(pane: Pane) -> impl IntoView {
+    let pattern = create_rw_signal(String::new());
+    let root = pane
+        .tree
+        .with_untracked(|tree| tree.root().path().to_owned());
+    let matches = create_memo(move |_| -> Result<Vec<PathBuf>, String> {
+        pattern.with(|pattern| {
+            if pattern.is_empty() {
+                return Ok(Vec::new());
+            }
+            pane.tree.with(|tree| {
+                let found = find_by_pattern(tree, pattern).map_err(|err| err.kind().to_string())?;
+                Ok(found
+                    .into_iter()
+                    .filter_map(|ix| Some(tree.get(ix)?.path().to_owned()))
+                    .collect::<Vec<_>>())
+            })
+        })
+    });
+    let listed = move || -> Vec<PathBuf> {
+        matches.with(|matches| {
+            matches
+                .as_ref()
+                .map(|paths| paths.iter().take(MAX_FIND_RESULTS).cloned().collect())
+                .unwrap_or_default()
+        })
+    };
+
+    let open_all = move || {
+        for path in listed() {
+            if let Err(err) = system_open::open(&path) {
+                let body = format!("{}: {}", path.display(), err);
+                notify::error(tr("Failed to open"), body, Vec::new());
+            }
+        }
+    };
+
+    let input = text_input(pattern)
+        .placeholder(tr("Find by pattern, like src/**/*.rs"))
+        .request_focus(|| ())
+        .on_key_down(
+            Key::Named(NamedKey::Escape),
+            |m| m.is_empty(),
+            move |_| pane.finding.set(false),
+        )
+        .style(|s| s.width_full());
+
+    let summary = (
+        label(move || {
+            matches.with(|matches| match matches {
+                Ok(paths) if paths.len() > MAX_FIND_RESULTS => tr_args(
+                    "{count} found, showing {shown}",
+                    &[("count", &paths.len()), ("shown", &MAX_FIND_RESULTS)],
+                ),
+                Ok(paths) => tr_args("{count} found", &[("count", &paths.len())]),
+                Err(err) => err.clone(),
+            })
+        })
+        .style(move |s| {
+            s.flex_grow(1.0)
+                .color(Color::GRAY)
+                .apply_if(matches.with(Result::is_err), |s| s.color(Color::RED))
+        }),
+        button(label(|| tr("Open All"))).action(open_all),
+        button(label(|| tr("Close"))).action(move || pane.finding.set(false)),
+    )
+        .h_stack()
+        .style(|s| s.items_center().gap(2.0).padding_horiz(4.0));
+
+    let results = dyn_stack(
+        listed,
+        |path| path.clone(),
+        move |path| {
+            let name = path
+                .strip_prefix(&root)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            label(move || name.clone())
+                .on_click_stop(move |_| {
+                    pane.finding.set(false);
+                    pane.jump_to(path.clone());
+                })
+                .style(|s| {
+                    s.height(ROW_HEIGHT)
+                        .padding_horiz(4.0)
+                        .items_center()
+                        .hover(|s| s.background(Color::LIGHT_GRAY))
+                })
+        },
+    )
+    .style(|s| s.flex_col().width_full());
+
+    (
+        input,
+        summary,
+        scroll(results).style(|s| s.flex_grow(1.0).min_height(0.0).width_full()),
+    )
+        .v_stack()
+        .style(|s| s.size_full().gap(2.0).padding(2.0).background(Color::WHITE))
+}
+
+/// A submenu opening `path` with the default application of the system, or
+/// one of the tools in the settings.
+fn open_with_menu(state: ExplorerState, path: PathBuf) -> Menu {
+    let open = |program: Option<String>| {
+        let path = path.clone();
+        move || {
+            let result = match &program {
+                Some(program) => system_open::open_with(program, &path),
+                None => system_open::open(&path),
+            };
+            if let Err(err) = result {
+                let body = format!("{}: {}", path.display(), err);
+                notify::error(tr("Failed to open"), body, Vec::new());
+            }
+        }
+    };
+
+    let mut menu = Menu::new(tr("Open With"))
+        .entry(MenuItem::new(tr("Default Application")).action(open(None)));
+    let tools = state
+        .settings
+        .with_untracked(|settings| settings.tools.clone());
+    if !tools.is_empty() {
+        menu = menu.separator();
+    }
+    for tool in tools {
+        menu = menu.entry(MenuItem::new(tool.clone()).action(open(Some(tool))));
+    }
+    menu
+}
+
+/// A submenu creating a new file in `dir`, either empty or from one of the
+/// templates in the config directory.
+fn new_file_menu(state: ExplorerState, pane: Pane, dir: PathBuf) -> Menu {
+    let menu = Menu::new(tr("New File")).entry(MenuItem::new(tr("Empty File")).action({
+        let dir = dir.clone();
+        move || state.new_file(pane, &dir, None)
+    }));
+
+    let templates = crate::config::templates();
+    if templates.is_empty() {
+        return menu;
+    }
+
+    templates
+        .into_iter()
+        .fold(menu.separator(), |menu, template| {
+            let name = template
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let dir = dir.clone();
+            menu.entry(
+                MenuItem::new(name).action(move || state.new_file(pane, &dir, Some(&template))),
+            )
+        })
+}
+
+/// A text input that renames the file or directory at `path`.
+///
+/// Enter renames it, while Escape or clicking away cancels. The input is
+/// outlined in red while the name is invalid.
+fn rename_input(state: ExplorerState, pane: Pane, path: PathBuf) -> impl IntoView {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let buffer = create_rw_signal(name);
+    let is_valid = {
+        let path = path.clone();
+        move || buffer.with(|name| pane.rename_target(&path, name).is_ok())
+    };
+
+    text_input(buffer)
+        .request_focus(|| ())
+        .on_key_down(
+            Key::Named(NamedKey::Enter),
+            |m| m.is_empty(),
+            move |_| {
+                let Ok(to) = buffer.with_untracked(|name| pane.rename_target(&path, name)) else {
+                    return;
+                };
+                pane.renaming.set(None);
+                if to != path {
+                    state.rename(path.clone(), to.clone());
+                    pane.reveal(to);
+                }
+            },
+        )
+        .on_key_down(
+            Key::Named(NamedKey::Escape),
+            |m| m.is_empty(),
+            move |_| pane.renaming.set(None),
+        )
+        .on_event_cont(EventListener::FocusLost, move |_| pane.renaming.set(None))
+        .style(move |s| {
+            s.height(ROW_HEIGHT - 2.0)
+                .min_width(0.0)
+                .flex_grow(1.0)
+                .apply_if(!is_valid(), |s| s.border_color(Color::RED))
+        })
+}
+
+/// Asks the user to type the name of a protected path before an operation on
+/// it goes ahead. Hidden while nothing needs confirming.
+pub fn confirm_view(state: ExplorerState) -> impl IntoView {
+    let confirming = state.confirming;
+
+    let dialog = dyn_container(
+        move || confirming.get(),
+        move |op| {
+            let (message, action, path) = match op {
+                Some(FileOp::Rename { from, .. }) => (
+                    "{path} is protected. Type \"{name}\" to move it.",
+                    tr("Move"),
+                    from,
+                ),
+                Some(FileOp::Delete { path }) => (
+                    "{path} is protected. Type \"{name}\" to delete it.",
+                    tr("Delete"),
+                    path,
+                ),
+                _ => return empty().into_any(),
+            };
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let message = tr_args(message, &[("path", &path.display()), ("name", &name)]);
+
+            let typed = create_rw_signal(String::new());
+            let wrong = create_rw_signal(false);
+            (
+                label(move || message.clone()),
+                text_input(typed)
+                    .request_focus(|| ())
+                    .on_key_down(
+                        Key::Named(NamedKey::Enter),
+                        |m| m.is_empty(),
+                        move |_| wrong.set(!state.confirm(&typed.get_untracked())),
+                    )
+                    .on_key_down(
+                        Key::Named(NamedKey::Escape),
+                        |m| m.is_empty(),
+                        move |_| confirming.set(None),
+                    )
+                    .style(move |s| {
+                        s.width_full()
+                            .apply_if(wrong.get(), |s| s.border_color(Color::RED))
+                    }),
+                (
+                    button(tr("Cancel")).action(move || confirming.set(None)),
+                    button(action)
+                        .action(move || wrong.set(!state.confirm(&typed.get_untracked()))),
+                )
+                    .h_stack()
+                    .style(|s| s.gap(4.0).justify_end()),
+            )
+                .v_stack()
+                .style(|s| {
+                    s.width(360.0)
+                        .gap(6.0)
+                        .padding(8.0)
+                        .border(1.0)
+                        .background(Color::WHITE)
+                })
+                .into_any()
+        },
+    );
+
+    container(dialog).style(move |s| {
+        s.absolute()
+            .inset(0.0)
+            .items_center()
+            .justify_center()
+            .background(Color::BLACK.multiply_alpha(0.3))
+            .apply_if(confirming.with(Option::is_none), |s| s.hide())
+    })
+}
+
+/// Finds the README of a directory.
+fn find_readme(tree: RwSignal<Tree>, ix: TreeIndex) -> Option<PathBuf> {
+    tree.with_untracked(|tree| {
+        tree.children(ix)
+            .map(|(_, child)| child.path())
+            .find(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        ["README.md", "README.txt", "README"]
+                            .iter()
+                            .any(|readme| name.eq_ignore_ascii_case(readme))
+                    })
+            })
+            .map(Path::to_owned)
+    })
 }
@@ -0,0 +1,556 @@
+//! Source control, through the `git` command line.
+//!
+//! The source control panel lists the changed files of the workspace, which
+//! can be staged, unstaged and committed. The branch is shown in the status
+//! bar, where other branches can be checked out.
+//!
+//! There is no git2 integration to build on, so git is run as a subprocess.
+//! Only the functions below run it, which keeps a switch to a library to this
+//! module. Status is read on a background thread so large repositories do not
+//! block the window.
+
+use floem::ext_event::create_ext_action;
+use floem::menu::{Menu, MenuItem};
+use floem::prelude::*;
+use floem::reactive::Scope;
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::locale::{tr, tr_args};
+
+/// How a file differs from `HEAD` or from the index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    /// The file has merge conflicts.
+    Conflicted,
+}
+
+impl ChangeKind {
+    /// Reads a status code of `git status --porcelain`.
+    fn from_code(code: u8) -> Option<ChangeKind> {
+        match code {
+            b'M' | b'T' => Some(ChangeKind::Modified),
+            b'A' => Some(ChangeKind::Added),
+            b'D' => Some(ChangeKind::Deleted),
+            b'R' | b'C' => Some(ChangeKind::Renamed),
+            b'?' => Some(ChangeKind::Untracked),
+            b'U' => Some(ChangeKind::Conflicted),
+            _ => None,
+        }
+    }
+
+    /// The letter the change is listed with, like `M`.
+    pub fn letter(self) -> &'static str {
+        match self {
+            ChangeKind::Modified => "M",
+            ChangeKind::Added => "A",
+            ChangeKind::Deleted => "D",
+            ChangeKind::Renamed => "R",
+            ChangeKind::Untracked => "U",
+            ChangeKind::Conflicted => "!",
+        }
+    }
+}
+
+/// A changed file in the repository.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// The path of the file, relative to the root of the repository.
+    pub path: PathBuf,
+    /// The change staged to be committed, if any.
+    pub staged: Option<ChangeKind>,
+    /// The change in the working tree that is not staged, if any.
+    pub unstaged: Option<ChangeKind>,
+}
+
+/// Lists the changed files of the repository at `root`.
+pub fn status(root: &Path) -> Result<Vec<Change>, Error> {
+    git(root, ["status", "--porcelain=v1", "-z"]).map(|out| parse_status(&out))
+}
+
+/// Reads the output of `git status --porcelain=v1 -z`.
+fn parse_status(out: &[u8]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut entries = out.split(|b| *b == 0);
+    while let Some(entry) = entries.next() {
+        let [x, y, b' ', path @ ..] = entry else {
+            continue;
+        };
+        let (x, y) = (*x, *y);
+        let path = PathBuf::from(String::from_utf8_lossy(path).into_owned());
+        // renames and copies are followed by the path they came from
+        if matches!(x, b'R' | b'C') {
+            entries.next();
+        }
+
+        let conflicted = x == b'U' || y == b'U' || (x, y) == (b'A', b'A') || (x, y) == (b'D', b'D');
+        let change = if conflicted {
+            Change {
+                path,
+                staged: None,
+                unstaged: Some(ChangeKind::Conflicted),
+            }
+        } else if x == b'?' {
+            Change {
+                path,
+                staged: None,
+                unstaged: Some(ChangeKind::Untracked),
+            }
+        } else {
+            Change {
+                path,
+                staged: ChangeKind::from_code(x),
+                unstaged: ChangeKind::from_code(y),
+            }
+        };
+        changes.push(change);
+    }
+    changes
+}
+
+/// Gets the branch checked out in the repository at `root`, or `None` if no
+/// branch is checked out.
+pub fn branch(root: &Path) -> Result<Option<String>, Error> {
+    let out = git(root, ["branch", "--show-current"])?;
+    let branch = String::from_utf8_lossy(&out).trim().to_owned();
+    Ok(Some(branch).filter(|branch| !branch.is_empty()))
+}
+
+/// Gets the root of the repository that `root` is in, which the paths of
+/// [`Change`]s are relative to.
+pub fn toplevel(root: &Path) -> Result<PathBuf, Error> {
+    let out = git(root, ["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&out).trim()))
+}
+
+/// Lists the local branches of the repository at `root`.
+pub fn branches(root: &Path) -> Result<Vec<String>, Error> {
+    let out = git(root, ["branch", "--format=%(refname:short)"])?;
+    Ok(String::from_utf8_lossy(&out)
+        .lines()
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Stages the file at `path`, relative to the root of the repository, like
+/// the paths in [`Change`].
+pub fn stage(root: &Path, path: &Path) -> Result<(), Error> {
+    git(
+        root,
+        [OsStr::new("add"), OsStr::new("--"), &top_pathspec(path)],
+    )
+    .map(drop)
+}
+
+/// Unstages the file at `path`, relative to the root of the repository,
+/// keeping its changes in the working tree.
+pub fn unstage(root: &Path, path: &Path) -> Result<(), Error> {
+    git(
+        root,
+        [
+            OsStr::new("restore"),
+            OsStr::new("--staged"),
+            OsStr::new("--"),
+            &top_pathspec(path),
+        ],
+    )
+    .map(drop)
+}
+
+/// A pathspec for `path` relative to the root of the repository, since the
+/// workspace may be a directory inside of it.
+fn top_pathspec(path: &Path) -> OsString {
+    let mut pathspec = OsString::from(":(top)");
+    pathspec.push(path);
+    pathspec
+}
+
+/// Commits the staged changes with `message`.
+pub fn commit(root: &Path, message: &str) -> Result<(), Error> {
+    git(root, ["commit", "-m", message]).map(drop)
+}
+
+/// Checks out `branch`.
+pub fn checkout(root: &Path, branch: &str) -> Result<(), Error> {
+    check_branch_name(root, branch)?;
+    git(root, ["switch", branch]).map(drop)
+}
+
+/// Creates `branch` from the current commit, and checks it out.
+pub fn create_branch(root: &Path, branch: &str) -> Result<(), Error> {
+    check_branch_name(root, branch)?;
+    git(root, ["switch", "-c", branch]).map(drop)
+}
+
+/// Fails if `branch` is not a valid branch name. Names starting with `-`
+/// would be read as options, so they are rejected before running git.
+fn check_branch_name(root: &Path, branch: &str) -> Result<(), Error> {
+    let invalid = || Error::InvalidBranch(branch.to_owned());
+    if branch.is_empty() || branch.starts_with('-') {
+        return Err(invalid());
+    }
+    git(root, ["check-ref-format", "--branch", branch])
+        .map(drop)
+        .map_err(|_| invalid())
+}
+
+/// Runs git in the repository at `root`, returning what it printed.
+fn git(root: &Path, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Result<Vec<u8>, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(Error::Io)?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        return Err(Error::Git(message));
+    }
+    Ok(output.stdout)
+}
+
+/// The source control state of a workspace.
+#[derive(Clone, Copy)]
+pub struct SourceControl {
+    root: RwSignal<PathBuf>,
+    /// The branch checked out, if any.
+    pub branch: RwSignal<Option<String>>,
+    /// The changed files, as of the last refresh.
+    pub changes: RwSignal<Vec<Change>>,
+    /// The root of the repository, which the paths of the changes are
+    /// relative to.
+    pub toplevel: RwSignal<Option<PathBuf>>,
+    /// The local branches, as of the last refresh.
+    pub branches: RwSignal<Vec<String>>,
+    /// The last error git reported, if any.
+    pub error: RwSignal<Option<String>>,
+}
+
+impl SourceControl {
+    /// Reads the status of the repository at `root`, in the background.
+    pub fn new(root: impl Into<PathBuf>) -> SourceControl {
+        let scm = SourceControl {
+            root: create_rw_signal(root.into()),
+            branch: create_rw_signal(None),
+            changes: create_rw_signal(Vec::new()),
+            toplevel: create_rw_signal(None),
+            branches: create_rw_signal(Vec::new()),
+            error: create_rw_signal(None),
+        };
+        scm.refresh();
+        scm
+    }
+
+    /// Reads the branches and changed files again, in the background.
+    ///
+    /// Clears the last error if they are read.
+    pub fn refresh(&self) {
+        self.run(|_| Ok(()), || ());
+    }
+
+    /// Runs `action` in the repository in the background, then refreshes.
+    /// Calls `done` if it succeeded, or else shows the error.
+    pub fn run(
+        &self,
+        action: impl FnOnce(&Path) -> Result<(), Error> + Send + 'static,
+        done: impl FnOnce() + 'static,
+    ) {
+        let error = self.error;
+        self.background(action, move |result| {
+            error.set(result.as_ref().err().map(Error::to_string));
+            if result.is_ok() {
+                done();
+            }
+        });
+    }
+
+    /// Runs `action` and reads the status and branches on a background
+    /// thread, then passes the result of `action` to `done` and shows the
+    /// status.
+    fn background(
+        &self,
+        action: impl FnOnce(&Path) -> Result<(), Error> + Send + 'static,
+        done: impl FnOnce(Result<(), Error>) + 'static,
+    ) {
+        let scm = *self;
+        let finish = create_ext_action(Scope::current(), move |(result, status)| {
+            done(result);
+            match status {
+                Ok(Status {
+                    branch,
+                    toplevel,
+                    changes,
+                    branches,
+                }) => {
+                    scm.branch.set(branch);
+                    scm.toplevel.set(Some(toplevel));
+                    scm.changes.set(changes);
+                    scm.branches.set(branches);
+                }
+                Err(err) => {
+                    scm.branch.set(None);
+                    scm.toplevel.set(None);
+                    scm.changes.set(Vec::new());
+                    scm.branches.set(Vec::new());
+                    scm.error.set(Some(Error::to_string(&err)));
+                }
+            }
+        });
+
+        let root = self.root.get_untracked();
+        std::thread::spawn(move || {
+            let result = action(&root);
+            let status = Status::read(&root);
+            finish((result, status));
+        });
+    }
+}
+
+/// Everything read from the repository on a refresh.
+struct Status {
+    branch: Option<String>,
+    toplevel: PathBuf,
+    changes: Vec<Change>,
+    branches: Vec<String>,
+}
+
+impl Status {
+    fn read(root: &Path) -> Result<Status, Error> {
+        Ok(Status {
+            branch: branch(root)?,
+            toplevel: toplevel(root)?,
+            changes: status(root)?,
+            branches: branches(root)?,
+        })
+    }
+}
+
+/// The source control panel: the staged and unstaged changes, and a box to
+/// commit the staged changes with.
+pub fn source_control_view(scm: SourceControl) -> impl IntoView {
+    let message = create_rw_signal(String::new());
+    let new_branch = create_rw_signal(String::new());
+
+    let header = (
+        label(|| tr("Source Control")).style(|s| s.flex_grow(1.0)),
+        button(label(|| tr("Refresh"))).action(move || scm.refresh()),
+    )
+        .h_stack()
+        .style(|s| s.items_center().padding(4.0));
+
+    let error = label(move || scm.error.get().unwrap_or_default()).style(move |s| {
+        s.padding(4.0)
+            .color(Color::RED)
+            .apply_if(scm.error.with(Option::is_none), |s| s.hide())
+    });
+
+    let commit_box = (
+        text_input(message)
+            .placeholder(tr("Message"))
+            .style(|s| s.flex_grow(1.0)),
+        button(label(|| tr("Commit")))
+            .action(move || {
+                let text = message.get_untracked();
+                scm.run(
+                    move |root| commit(root, &text),
+                    move || message.set(String::new()),
+                );
+            })
+            .disabled(move || message.with(|message| message.trim().is_empty())),
+    )
+        .h_stack()
+        .style(|s| s.gap(4.0).padding(4.0));
+
+    let branch_box = (
+        text_input(new_branch)
+            .placeholder(tr("New branch"))
+            .style(|s| s.flex_grow(1.0)),
+        button(label(|| tr("Create"))).action(move || {
+            let name = new_branch.get_untracked();
+            scm.run(
+                move |root| create_branch(root, name.trim()),
+                move || new_branch.set(String::new()),
+            );
+        }),
+    )
+        .h_stack()
+        .style(|s| s.gap(4.0).padding(4.0));
+
+    let lists = (changes_view(scm, true), changes_view(scm, false)).v_stack();
+
+    (
+        header,
+        error,
+        commit_box,
+        branch_box,
+        scroll(lists).style(|s| s.flex_grow(1.0).min_height(0.0).width_full()),
+    )
+        .v_stack()
+        .style(|s| s.size_full())
+}
+
+/// The staged changes if `staged`, or else the unstaged ones. Each can be
+/// moved to the other list.
+fn changes_view(scm: SourceControl, staged: bool) -> impl IntoView {
+    let changes = move || {
+        scm.changes.with(|changes| {
+            changes
+                .iter()
+                .filter_map(|change| {
+                    let kind = if staged {
+                        change.staged
+                    } else {
+                        change.unstaged
+                    };
+                    Some((change.path.clone(), kind?))
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let title = move || {
+        let count = scm.changes.with(|changes| {
+            changes
+                .iter()
+                .filter(|change| {
+                    if staged {
+                        change.staged.is_some()
+                    } else {
+                        change.unstaged.is_some()
+                    }
+                })
+                .count()
+        });
+        if staged {
+            tr_args("Staged Changes ({count})", &[("count", &count)])
+        } else {
+            tr_args("Changes ({count})", &[("count", &count)])
+        }
+    };
+
+    let rows = dyn_stack(
+        changes,
+        |(path, kind)| (path.clone(), *kind),
+        move |(path, kind)| {
+            let name = path.display().to_string();
+            (
+                label(move || kind.letter()).style(|s| s.width(16.0).color(Color::GRAY)),
+                label(move || name.clone()).style(|s| s.flex_grow(1.0)),
+                button(label(move || if staged { "−" } else { "+" })).action(move || {
+                    let path = path.clone();
+                    if staged {
+                        scm.run(move |root| unstage(root, &path), || ());
+                    } else {
+                        scm.run(move |root| stage(root, &path), || ());
+                    }
+                }),
+            )
+                .h_stack()
+                .style(|s| s.items_center().padding_horiz(4.0).width_full())
+        },
+    )
+    .style(|s| s.flex_col().width_full());
+
+    (
+        label(title).style(|s| s.padding(4.0).color(Color::GRAY)),
+        rows,
+    )
+        .v_stack()
+        .style(|s| s.width_full())
+}
+
+/// A menu checking out one of the local branches.
+pub fn branch_menu(scm: SourceControl) -> Menu {
+    let current = scm.branch.get_untracked();
+    scm.branches
+        .get_untracked()
+        .into_iter()
+        .fold(Menu::new(""), |menu, branch| {
+            let name = if current.as_ref() == Some(&branch) {
+                format!("✓ {}", branch)
+            } else {
+                branch.clone()
+            };
+            menu.entry(MenuItem::new(name).action(move || {
+                let branch = branch.clone();
+                scm.run(move |root| checkout(root, &branch), || ());
+            }))
+        })
+}
+
+/// An error that can occur while running git.
+#[derive(Debug)]
+pub enum Error {
+    /// git could not be run.
+    Io(io::Error),
+    /// git failed, with this message.
+    Git(String),
+    /// A branch name is not valid.
+    InvalidBranch(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "failed to run git: {}", err),
+            Error::Git(message) => f.write_str(message),
+            Error::InvalidBranch(branch) => write!(f, "invalid branch name {:?}", branch),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let out = b" M maps/MAP01.wad\0R  MAPINFO.txt\0ZMAPINFO.txt\0?? notes.txt\0UU DECORATE\0MM textures/TEXTURES\0";
+        let change = |path: &str, staged, unstaged| Change {
+            path: PathBuf::from(path),
+            staged,
+            unstaged,
+        };
+
+        assert_eq!(
+            parse_status(out),
+            [
+                change("maps/MAP01.wad", None, Some(ChangeKind::Modified)),
+                change("MAPINFO.txt", Some(ChangeKind::Renamed), None),
+                change("notes.txt", None, Some(ChangeKind::Untracked)),
+                change("DECORATE", None, Some(ChangeKind::Conflicted)),
+                change(
+                    "textures/TEXTURES",
+                    Some(ChangeKind::Modified),
+                    Some(ChangeKind::Modified)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_branch_name() {
+        // rejected without running git
+        let root = Path::new("/nonexistent");
+        assert!(matches!(
+            create_branch(root, "--orphan"),
+            Err(Error::InvalidBranch(branch)) if branch == "--orphan"
+        ));
+        assert!(matches!(checkout(root, ""), Err(Error::InvalidBranch(_))));
+        assert_eq!(
+            top_pathspec(Path::new("maps/MAP01.wad")),
+            ":(top)maps/MAP01.wad"
+        );
+    }
+}
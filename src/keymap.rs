@@ -0,0 +1,440 @@
+//! Key bindings.
+//!
+//! A [`Keymap`] maps key chords to [`Command`]s. The defaults can be changed
+//! in `keymap.toml` in the [config directory](crate::config::config_dir):
+//!
+//! ```toml
+//! [bindings]
+//! "ctrl+z" = "explorer.undo"
+//! "f2" = "explorer.rename"
+//! # unbind a default
+//! "delete" = ""
+//! ```
+
+use floem::keyboard::{Key, KeyEvent, Modifiers, NamedKey};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// A command that can be bound to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// Previews the selected file, or closes the open preview.
+    TogglePreview,
+    /// Closes the open preview.
+    ClosePreview,
+    /// Starts renaming the selected node.
+    Rename,
+    /// Moves the selected node to the trash.
+    Delete,
+    /// Undoes the last file operation.
+    Undo,
+    /// Redoes the last undone file operation.
+    Redo,
+    /// Opens the selected directory.
+    Expand,
+    /// Closes the selected directory.
+    Collapse,
+    /// Closes every directory.
+    CollapseAll,
+    /// Opens every directory.
+    ExpandAll,
+    /// Switches between the tree and a flat list of files.
+    ToggleFlat,
+    /// Copies the selected node to the clipboard.
+    Copy,
+    /// Cuts the selected node to the clipboard.
+    Cut,
+    /// Pastes the clipboard into the selected directory.
+    Paste,
+    /// Copies the selected node next to itself.
+    Duplicate,
+    /// Creates an empty file in the selected directory.
+    NewFile,
+    /// Rescans the tree, keeping open directories open.
+    Refresh,
+    /// Pins the selected node to the favorites, or unpins it.
+    ToggleFavorite,
+    /// Copies the absolute path of the selected node to the system
+    /// clipboard.
+    CopyPath,
+    /// Copies the path of the selected node, relative to the root, to the
+    /// system clipboard.
+    CopyRelativePath,
+    /// Finds files by a glob pattern, or closes the search.
+    FindByPattern,
+    /// Opens the newest log file.
+    OpenLog,
+    /// Reads the settings and key bindings again.
+    ReloadSettings,
+    /// Goes back to the node visited before.
+    NavigateBack,
+    /// Goes forward to the node visited after.
+    NavigateForward,
+}
+
+impl Command {
+    /// Every command, paired with its id.
+    const ALL: [(Command, &'static str); 25] = [
+        (Command::TogglePreview, "explorer.toggle_preview"),
+        (Command::ClosePreview, "explorer.close_preview"),
+        (Command::Rename, "explorer.rename"),
+        (Command::Delete, "explorer.delete"),
+        (Command::Undo, "explorer.undo"),
+        (Command::Redo, "explorer.redo"),
+        (Command::Expand, "explorer.expand"),
+        (Command::Collapse, "explorer.collapse"),
+        (Command::CollapseAll, "explorer.collapse_all"),
+        (Command::ExpandAll, "explorer.expand_all"),
+        (Command::ToggleFlat, "explorer.toggle_flat"),
+        (Command::Copy, "explorer.copy"),
+        (Command::Cut, "explorer.cut"),
+        (Command::Paste, "explorer.paste"),
+        (Command::Duplicate, "explorer.duplicate"),
+        (Command::NewFile, "explorer.new_file"),
+        (Command::Refresh, "explorer.refresh"),
+        (Command::ToggleFavorite, "explorer.toggle_favorite"),
+        (Command::CopyPath, "explorer.copy_path"),
+        (Command::CopyRelativePath, "explorer.copy_relative_path"),
+        (Command::FindByPattern, "explorer.find_by_pattern"),
+        (Command::OpenLog, "app.open_log"),
+        (Command::ReloadSettings, "app.reload_settings"),
+        (Command::NavigateBack, "explorer.navigate_back"),
+        (Command::NavigateForward, "explorer.navigate_forward"),
+    ];
+
+    /// The id of the command used in the config file.
+    pub fn id(self) -> &'static str {
+        Command::ALL
+            .iter()
+            .find(|(command, _)| *command == self)
+            .map(|(_, id)| *id)
+            .expect("every command to have an id")
+    }
+
+    /// Finds the command with the id `id`.
+    pub fn from_id(id: &str) -> Option<Command> {
+        Command::ALL
+            .iter()
+            .find(|(_, command_id)| *command_id == id)
+            .map(|(command, _)| *command)
+    }
+}
+
+/// A key pressed with a set of modifiers, like `ctrl+z`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    key: Key,
+    modifiers: Modifiers,
+}
+
+impl KeyChord {
+    /// Creates a new `KeyChord`.
+    ///
+    /// Letters are matched regardless of case, so `shift+z` is pressed with
+    /// either `z` or `Z`. Other characters, like `?`, are typed with shift
+    /// already, so shift is ignored for them.
+    pub fn new(key: Key, mut modifiers: Modifiers) -> KeyChord {
+        let key = match key {
+            Key::Character(c) => {
+                let lower = c.to_lowercase();
+                if lower == c.to_uppercase() {
+                    modifiers -= Modifiers::SHIFT;
+                }
+                Key::Character(lower.into())
+            }
+            key => key,
+        };
+
+        KeyChord { key, modifiers }
+    }
+
+    /// The chord pressed in a key event.
+    pub fn from_event(event: &KeyEvent) -> KeyChord {
+        KeyChord::new(event.key.logical_key.clone(), event.modifiers)
+    }
+
+    /// Parses a chord like `ctrl+shift+f2`.
+    pub fn parse(s: &str) -> Option<KeyChord> {
+        let mut parts = s.split('+').map(str::trim).collect::<Vec<_>>();
+        let key = parts.pop()?;
+
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CONTROL,
+                "shift" => Modifiers::SHIFT,
+                "alt" => Modifiers::ALT,
+                "meta" | "cmd" | "super" => Modifiers::META,
+                _ => return None,
+            };
+        }
+
+        Some(KeyChord::new(parse_key(key)?, modifiers))
+    }
+}
+
+/// Parses the name of a key.
+fn parse_key(s: &str) -> Option<Key> {
+    let named = match s.to_lowercase().as_str() {
+        "space" => NamedKey::Space,
+        "enter" | "return" => NamedKey::Enter,
+        "escape" | "esc" => NamedKey::Escape,
+        "tab" => NamedKey::Tab,
+        "backspace" => NamedKey::Backspace,
+        "delete" | "del" => NamedKey::Delete,
+        "insert" => NamedKey::Insert,
+        "home" => NamedKey::Home,
+        "end" => NamedKey::End,
+        "pageup" => NamedKey::PageUp,
+        "pagedown" => NamedKey::PageDown,
+        "up" => NamedKey::ArrowUp,
+        "down" => NamedKey::ArrowDown,
+        "left" => NamedKey::ArrowLeft,
+        "right" => NamedKey::ArrowRight,
+        "f1" => NamedKey::F1,
+        "f2" => NamedKey::F2,
+        "f3" => NamedKey::F3,
+        "f4" => NamedKey::F4,
+        "f5" => NamedKey::F5,
+        "f6" => NamedKey::F6,
+        "f7" => NamedKey::F7,
+        "f8" => NamedKey::F8,
+        "f9" => NamedKey::F9,
+        "f10" => NamedKey::F10,
+        "f11" => NamedKey::F11,
+        "f12" => NamedKey::F12,
+        _ => {
+            let mut chars = s.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(Key::Character(c.to_string().into())),
+                _ => None,
+            };
+        }
+    };
+
+    Some(Key::Named(named))
+}
+
+/// A set of key bindings.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<KeyChord, Command>,
+}
+
+impl Keymap {
+    /// Loads the keymap from the config directory, on top of the defaults.
+    ///
+    /// Problems with the config file are reported and skipped.
+    pub fn load() -> Keymap {
+        let mut keymap = Keymap::default();
+
+        let Some(path) = crate::config::config_dir().map(|dir| dir.join("keymap.toml")) else {
+            return keymap;
+        };
+        if let Err(err) = keymap.load_file(&path) {
+            tracing::warn!(path = %path.display(), %err, "failed to load keymap");
+        }
+
+        keymap
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), Error> {
+        let s = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(Error::Io(err)),
+        };
+
+        for err in self.load_str(&s)? {
+            tracing::warn!(path = %path.display(), %err, "skipped key binding");
+        }
+        Ok(())
+    }
+
+    /// Adds the bindings in a TOML config to the keymap.
+    ///
+    /// Fails if the config is not valid TOML. Bindings that cannot be parsed
+    /// are skipped, and returned as errors.
+    pub fn load_str(&mut self, s: &str) -> Result<Vec<Error>, Error> {
+        let doc = s
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| Error::Toml(err.to_string()))?;
+
+        let Some(bindings) = doc.get("bindings").and_then(|item| item.as_table_like()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut errors = Vec::new();
+        for (chord, command) in bindings.iter() {
+            let Some(chord) = KeyChord::parse(chord) else {
+                errors.push(Error::InvalidChord(chord.to_owned()));
+                continue;
+            };
+            match command.as_str() {
+                // an empty command unbinds the chord
+                Some("") => {
+                    self.bindings.remove(&chord);
+                }
+                Some(id) => match Command::from_id(id) {
+                    Some(command) => {
+                        self.bindings.insert(chord, command);
+                    }
+                    None => errors.push(Error::UnknownCommand(id.to_owned())),
+                },
+                None => errors.push(Error::UnknownCommand(command.to_string())),
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Binds `chord` to `command`, replacing any existing binding.
+    pub fn bind(&mut self, chord: KeyChord, command: Command) {
+        self.bindings.insert(chord, command);
+    }
+
+    /// Gets the command bound to the chord pressed in `event`.
+    pub fn lookup(&self, event: &KeyEvent) -> Option<Command> {
+        self.get(&KeyChord::from_event(event))
+    }
+
+    /// Gets the command bound to `chord`.
+    pub fn get(&self, chord: &KeyChord) -> Option<Command> {
+        self.bindings.get(chord).copied()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+
+        for (chord, command) in [
+            ("space", Command::TogglePreview),
+            ("escape", Command::ClosePreview),
+            ("f2", Command::Rename),
+            ("delete", Command::Delete),
+            ("right", Command::Expand),
+            ("left", Command::Collapse),
+            ("ctrl+z", Command::Undo),
+            ("ctrl+shift+z", Command::Redo),
+            ("ctrl+y", Command::Redo),
+            ("ctrl+c", Command::Copy),
+            ("ctrl+x", Command::Cut),
+            ("ctrl+v", Command::Paste),
+            ("ctrl+d", Command::Duplicate),
+            ("ctrl+n", Command::NewFile),
+            ("f5", Command::Refresh),
+            ("ctrl+b", Command::ToggleFavorite),
+            ("ctrl+alt+c", Command::CopyPath),
+            ("ctrl+shift+alt+c", Command::CopyRelativePath),
+            ("ctrl+p", Command::FindByPattern),
+            ("alt+left", Command::NavigateBack),
+            ("alt+right", Command::NavigateForward),
+        ] {
+            keymap.bind(KeyChord::parse(chord).expect("valid chord"), command);
+        }
+
+        keymap
+    }
+}
+
+/// An error that can occur while loading a keymap.
+#[derive(Debug)]
+pub enum Error {
+    /// The config file is not valid TOML.
+    Toml(String),
+    /// A key chord could not be parsed.
+    InvalidChord(String),
+    /// A binding names a command that does not exist.
+    UnknownCommand(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Toml(err) => f.write_str(err),
+            Error::InvalidChord(chord) => write!(f, "invalid key chord {:?}", chord),
+            Error::UnknownCommand(id) => write!(f, "unknown command {}", id),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chord() {
+        assert_eq!(
+            KeyChord::parse("Ctrl+Shift+Z"),
+            Some(KeyChord::new(
+                Key::Character("z".into()),
+                Modifiers::CONTROL | Modifiers::SHIFT
+            ))
+        );
+        assert_eq!(
+            KeyChord::parse("f2"),
+            Some(KeyChord::new(Key::Named(NamedKey::F2), Modifiers::empty()))
+        );
+        assert_eq!(KeyChord::parse("shift+?"), KeyChord::parse("?"));
+        assert_eq!(KeyChord::parse("hyper+z"), None);
+        assert_eq!(KeyChord::parse("ctrl+"), None);
+    }
+
+    #[test]
+    fn test_load_str() {
+        let mut keymap = Keymap::default();
+        let errors = keymap
+            .load_str(
+                r#"
+                [bindings]
+                "ctrl+r" = "explorer.rename"
+                "delete" = ""
+                "ctrl+q" = "editor.quit"
+                "#,
+            )
+            .unwrap();
+
+        let chord = |s| KeyChord::parse(s).unwrap();
+        assert_eq!(keymap.get(&chord("ctrl+r")), Some(Command::Rename));
+        assert_eq!(keymap.get(&chord("f2")), Some(Command::Rename));
+        assert_eq!(keymap.get(&chord("delete")), None);
+        assert!(matches!(&errors[..], [Error::UnknownCommand(id)] if id == "editor.quit"));
+        assert!(keymap.load_str("[bindings").is_err());
+    }
+
+    #[test]
+    fn test_pressed_chord() {
+        // as built by `KeyChord::from_event`, which gets the shifted letter
+        let pressed = |c: &str, modifiers| KeyChord::new(Key::Character(c.into()), modifiers);
+        let ctrl = Modifiers::CONTROL;
+        let shift = Modifiers::SHIFT;
+        let alt = Modifiers::ALT;
+
+        assert_eq!(
+            Some(pressed("Z", ctrl | shift)),
+            KeyChord::parse("ctrl+shift+z")
+        );
+        assert_ne!(Some(pressed("z", ctrl)), KeyChord::parse("ctrl+shift+z"));
+
+        let keymap = Keymap::default();
+        assert_eq!(keymap.get(&pressed("z", ctrl)), Some(Command::Undo));
+        assert_eq!(keymap.get(&pressed("Z", ctrl | shift)), Some(Command::Redo));
+        assert_eq!(
+            keymap.get(&pressed("c", ctrl | alt)),
+            Some(Command::CopyPath)
+        );
+        assert_eq!(
+            keymap.get(&pressed("C", ctrl | shift | alt)),
+            Some(Command::CopyRelativePath)
+        );
+    }
+}
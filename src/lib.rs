@@ -1,4 +1,13 @@
 //! Mobius app.
 
 pub mod app;
+pub mod config;
 pub mod file_explorer;
+pub mod git;
+pub mod keymap;
+pub mod locale;
+pub mod logging;
+pub mod notify;
+pub mod plugin;
+pub mod system_open;
+pub mod workspace;
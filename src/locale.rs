@@ -0,0 +1,211 @@
+//! Translations of user-facing text.
+//!
+//! Text is looked up by its English message, gettext-style, in a locale
+//! bundle at `locales/<language>.toml` in the [config directory]:
+//!
+//! ```toml
+//! "Collapse" = "Réduire"
+//! "{count} found" = "{count} trouvés"
+//! ```
+//!
+//! The language is `language` in `settings.toml`, or else the language of
+//! the system. Messages missing from the bundle are shown in English.
+//!
+//! [config directory]: crate::config::config_dir
+
+use floem::reactive::{create_rw_signal, RwSignal, SignalGet, SignalUpdate, SignalWith};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::config::{self, config_dir};
+
+/// The language of the messages in the source.
+pub const ENGLISH: &str = "en";
+
+thread_local! {
+    /// The locale text is translated to. Reading it from a view updates the
+    /// view when the language is switched.
+    static LOCALE: RwSignal<Rc<Locale>> = create_rw_signal(Rc::new(Locale::english()));
+}
+
+/// The translations of a single language.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Locale {
+    language: String,
+    /// Translations, by English message.
+    messages: HashMap<String, String>,
+}
+
+impl Locale {
+    /// The locale of the source, which translates nothing.
+    pub fn english() -> Locale {
+        Locale {
+            language: ENGLISH.to_owned(),
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Loads the bundle of `language` from the config directory.
+    pub fn load(language: &str) -> Result<Locale, config::Error> {
+        if language == ENGLISH {
+            return Ok(Locale::english());
+        }
+
+        let path = bundle_path(language).ok_or_else(|| {
+            config::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no config directory",
+            ))
+        })?;
+        let s = std::fs::read_to_string(path).map_err(config::Error::Io)?;
+        Locale::load_str(language, &s)
+    }
+
+    /// Reads a bundle of `language` from a TOML string of translations.
+    ///
+    /// Fails if it is not valid TOML, or a translation is not a string.
+    pub fn load_str(language: &str, s: &str) -> Result<Locale, config::Error> {
+        let doc = s
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| config::Error::Toml(err.to_string()))?;
+
+        let messages = doc
+            .iter()
+            .map(|(message, value)| match value.as_str() {
+                Some(translation) => Ok((message.to_owned(), translation.to_owned())),
+                None => Err(config::Error::InvalidValue(message.to_owned())),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Locale {
+            language: language.to_owned(),
+            messages,
+        })
+    }
+
+    /// The language of the locale, like `en` or `pt-BR`.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Translates `message`, falling back to the message itself.
+    pub fn get<'a>(&'a self, message: &'a str) -> &'a str {
+        self.messages.get(message).map_or(message, String::as_str)
+    }
+}
+
+/// The path of the bundle of `language`, if there is a config directory.
+fn bundle_path(language: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("locales").join(format!("{}.toml", language)))
+}
+
+/// The languages there are bundles for, English first.
+pub fn languages() -> Vec<String> {
+    let mut languages = config_dir()
+        .and_then(|dir| std::fs::read_dir(dir.join("locales")).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .filter(|language| language != ENGLISH)
+        .collect::<Vec<_>>();
+    languages.sort();
+    languages.insert(0, ENGLISH.to_owned());
+    languages
+}
+
+/// Switches to the language in the settings, or else the language of the
+/// system, if there is a bundle for it.
+pub fn init() {
+    let configured = config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join("settings.toml")).ok())
+        .and_then(|s| s.parse::<toml_edit::DocumentMut>().ok())
+        .and_then(|doc| doc.get("language")?.as_str().map(str::to_owned));
+    if let Some(language) = configured {
+        set_language(&language);
+        return;
+    }
+
+    // `pt-BR` falls back to `pt`
+    let Some(system) = sys_locale::get_locale() else {
+        return;
+    };
+    let available = languages();
+    let language = [
+        system.as_str(),
+        system.split(['-', '_']).next().unwrap_or(""),
+    ]
+    .into_iter()
+    .find(|language| available.iter().any(|available| available == language));
+    if let Some(language) = language {
+        set_language(language);
+    }
+}
+
+/// Switches to `language`. Problems with its bundle are reported, and the
+/// language is left as it was.
+pub fn set_language(language: &str) {
+    match Locale::load(language) {
+        Ok(locale) => LOCALE.with(|current| current.set(Rc::new(locale))),
+        Err(err) => tracing::warn!(language, %err, "failed to load language"),
+    }
+}
+
+/// The current language.
+pub fn language() -> String {
+    LOCALE.with(|locale| locale.with(|locale| locale.language().to_owned()))
+}
+
+/// Translates `message` to the current language.
+pub fn tr(message: &str) -> String {
+    LOCALE.with(|locale| locale.get().get(message).to_owned())
+}
+
+/// Translates `message`, then fills in its `{name}` placeholders.
+pub fn tr_args(message: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    fill(&tr(message), args)
+}
+
+/// Fills in the `{name}` placeholders of `template`. Unknown placeholders are
+/// left as they are.
+fn fill(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = template.to_owned();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_str() {
+        let locale = Locale::load_str(
+            "fr",
+            r#"
+            "Collapse" = "Réduire"
+            "{count} found" = "{count} trouvés"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(locale.get("Collapse"), "Réduire");
+        assert_eq!(locale.get("Expand"), "Expand");
+        assert_eq!(
+            fill(locale.get("{count} found"), &[("count", &3)]),
+            "3 trouvés"
+        );
+        assert_eq!(fill("{count} of {total}", &[("count", &3)]), "3 of {total}");
+        assert!(matches!(
+            Locale::load_str("fr", "\"Collapse\" = 1"),
+            Err(config::Error::InvalidValue(message)) if message == "Collapse"
+        ));
+    }
+}
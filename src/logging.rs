@@ -0,0 +1,178 @@
+//! Logging and crash reports.
+//!
+//! Logs are written to stderr, and to `logs/mobius.<date>.log` in the
+//! [config directory], which is rotated daily and kept for a week. If the app
+//! panics, a crash report with a backtrace, the open workspaces and the last
+//! commands run is written next to the logs.
+//!
+//! [config directory]: crate::config::config_dir
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::config::config_dir;
+
+/// The most log files kept.
+const MAX_LOG_FILES: usize = 7;
+/// The most commands listed in a crash report.
+const MAX_COMMANDS: usize = 20;
+
+/// The workspaces opened since the app started.
+static WORKSPACES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+/// The last commands run, oldest first.
+static COMMANDS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// The directory logs and crash reports are written to, if there is a config
+/// directory.
+pub fn log_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("logs"))
+}
+
+/// Starts logging, and writes crash reports on panics.
+///
+/// Logs are written to the log file until the returned guard is dropped.
+pub fn init() -> Option<WorkerGuard> {
+    install_panic_hook();
+
+    let appender = log_dir().map(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("mobius")
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+    });
+    let (file, guard) = match appender {
+        Some(Ok(appender)) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        Some(Err(err)) => {
+            eprintln!("failed to open the log file: {}", err);
+            (None, None)
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file)
+        .with(LevelFilter::INFO)
+        .init();
+    guard
+}
+
+/// The newest log file, if any.
+pub fn latest_log() -> Option<PathBuf> {
+    std::fs::read_dir(log_dir()?)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with("mobius.") && name.ends_with(".log"))
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Lists the workspace at `root` in crash reports.
+pub fn opened_workspace(root: &Path) {
+    tracing::info!(root = %root.display(), "opened workspace");
+    WORKSPACES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(root.to_owned());
+}
+
+/// Lists the command `id` in crash reports.
+pub fn ran_command(id: &str) {
+    tracing::debug!(id, "running command");
+    let mut commands = COMMANDS.lock().unwrap_or_else(PoisonError::into_inner);
+    if commands.len() == MAX_COMMANDS {
+        commands.pop_front();
+    }
+    commands.push_back(id.to_owned());
+}
+
+/// Writes a crash report before the default panic message.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = crash_report(&info.to_string(), &Backtrace::force_capture());
+        match write_crash_report(&report) {
+            Ok(path) => eprintln!("crash report written to {}", path.display()),
+            Err(err) => eprintln!("failed to write crash report: {}", err),
+        }
+        default_hook(info);
+    }));
+}
+
+/// Writes `report` to a new file in the log directory.
+fn write_crash_report(report: &str) -> std::io::Result<PathBuf> {
+    let dir = log_dir()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no config directory"))?;
+    std::fs::create_dir_all(&dir)?;
+
+    let name = chrono::Local::now().format("crash-%Y%m%d-%H%M%S.txt");
+    let path = dir.join(name.to_string());
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Formats a crash report for a panic.
+fn crash_report(panic: &str, backtrace: &Backtrace) -> String {
+    // the panic may have happened while these were locked
+    let workspaces = WORKSPACES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|root| format!("  {}\n", root.display()))
+        .collect::<String>();
+    let commands = COMMANDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|id| format!("  {}\n", id))
+        .collect::<String>();
+
+    format!(
+        "mobius {} crashed\n\n{}\n\nworkspaces:\n{}\nrecent commands:\n{}\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        panic,
+        workspaces,
+        commands,
+        backtrace
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_report() {
+        opened_workspace(Path::new("/var/doom"));
+        for i in 0..MAX_COMMANDS + 1 {
+            ran_command(&format!("explorer.command_{}", i));
+        }
+
+        let report = crash_report("panicked at src/app.rs:1:1", &Backtrace::disabled());
+        assert!(report.contains("panicked at src/app.rs:1:1"));
+        assert!(report.contains("workspaces:\n  /var/doom\n"));
+        assert!(!report.contains("explorer.command_0\n"));
+        assert!(report.contains(&format!("explorer.command_{}\n", MAX_COMMANDS)));
+    }
+}
@@ -1,3 +1,3 @@
 fn main() {
-    floem::launch(mobius::app::app_view);
+    mobius::app::launch();
 }
@@ -0,0 +1,273 @@
+//! Notifications shown to the user.
+//!
+//! A notification pops up as a toast in the corner of every window, and is
+//! kept in the notification history until it is cleared. Every notification
+//! is logged too.
+
+use floem::action::exec_after;
+use floem::prelude::*;
+use floem::reactive::{create_rw_signal, RwSignal, SignalGet, SignalUpdate, SignalWith};
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::locale::tr;
+
+/// The most notifications kept in the history.
+const MAX_HISTORY: usize = 100;
+/// How long a toast is shown for, unless it is an error.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+thread_local! {
+    /// Every notification not cleared from the history, oldest first.
+    static HISTORY: RwSignal<Vec<Notification>> = create_rw_signal(Vec::new());
+    /// The ids of the notifications shown as toasts.
+    static TOASTS: RwSignal<Vec<u64>> = create_rw_signal(Vec::new());
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// How serious a notification is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    /// Something failed. Error toasts stay until they are dismissed.
+    Error,
+}
+
+/// A button on a notification.
+#[derive(Clone)]
+pub struct Action {
+    pub label: String,
+    run: Rc<dyn Fn()>,
+}
+
+impl Action {
+    /// Creates a button labeled `label`, running `run` when clicked.
+    pub fn new(label: impl Into<String>, run: impl Fn() + 'static) -> Action {
+        Action {
+            label: label.into(),
+            run: Rc::new(run),
+        }
+    }
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Action")
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message shown to the user.
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub id: u64,
+    pub level: Level,
+    pub title: String,
+    pub body: String,
+    pub actions: Vec<Action>,
+    pub time: chrono::DateTime<chrono::Local>,
+}
+
+/// Tells the user something happened.
+pub fn info(title: impl Into<String>, body: impl Into<String>, actions: Vec<Action>) {
+    notify(Level::Info, title.into(), body.into(), actions);
+}
+
+/// Warns the user about something.
+pub fn warn(title: impl Into<String>, body: impl Into<String>, actions: Vec<Action>) {
+    notify(Level::Warning, title.into(), body.into(), actions);
+}
+
+/// Tells the user something failed.
+pub fn error(title: impl Into<String>, body: impl Into<String>, actions: Vec<Action>) {
+    notify(Level::Error, title.into(), body.into(), actions);
+}
+
+fn notify(level: Level, title: String, body: String, actions: Vec<Action>) {
+    match level {
+        Level::Info => tracing::info!(%title, %body, "notified"),
+        Level::Warning => tracing::warn!(%title, %body, "notified"),
+        Level::Error => tracing::error!(%title, %body, "notified"),
+    }
+
+    let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+    HISTORY.with(|history| {
+        history.update(|history| {
+            history.push(Notification {
+                id,
+                level,
+                title,
+                body,
+                actions,
+                time: chrono::Local::now(),
+            });
+            if history.len() > MAX_HISTORY {
+                history.remove(0);
+            }
+        })
+    });
+    TOASTS.with(|toasts| toasts.update(|toasts| toasts.push(id)));
+
+    if level != Level::Error {
+        exec_after(TOAST_DURATION, move |_| dismiss(id));
+    }
+}
+
+/// Hides the toast of the notification `id`. It stays in the history.
+pub fn dismiss(id: u64) {
+    TOASTS.with(|toasts| toasts.update(|toasts| toasts.retain(|toast| *toast != id)));
+}
+
+/// Forgets every notification.
+pub fn clear() {
+    HISTORY.with(|history| history.set(Vec::new()));
+    TOASTS.with(|toasts| toasts.set(Vec::new()));
+}
+
+/// The notifications in the history, oldest first.
+pub fn history() -> Vec<Notification> {
+    HISTORY.with(|history| history.get())
+}
+
+/// The notifications shown as toasts, oldest first.
+pub fn toasts() -> Vec<Notification> {
+    let history = HISTORY.with(|history| *history);
+    TOASTS.with(|toasts| {
+        toasts.with(|toasts| {
+            history.with(|history| {
+                history
+                    .iter()
+                    .filter(|notification| toasts.contains(&notification.id))
+                    .cloned()
+                    .collect()
+            })
+        })
+    })
+}
+
+/// The toasts, stacked in the bottom right corner of the window above
+/// everything else.
+pub fn toasts_view() -> impl IntoView {
+    dyn_stack(
+        toasts,
+        |notification| notification.id,
+        |notification| notification_view(notification, true),
+    )
+    .style(|s| {
+        s.absolute()
+            .inset_bottom(8.0)
+            .inset_right(8.0)
+            .width(280.0)
+            .flex_col()
+            .gap(4.0)
+    })
+}
+
+/// The history of notifications, newest first.
+pub fn history_view() -> impl IntoView {
+    let list = dyn_stack(
+        || history().into_iter().rev().collect::<Vec<_>>(),
+        |notification| notification.id,
+        |notification| notification_view(notification, false),
+    )
+    .style(|s| s.flex_col().gap(4.0).width_full());
+
+    (
+        (
+            label(|| tr("Notifications")).style(|s| s.flex_grow(1.0)),
+            button(label(|| tr("Clear"))).action(clear),
+        )
+            .h_stack()
+            .style(|s| s.items_center().padding(4.0)),
+        scroll(list).style(|s| s.flex_grow(1.0).min_height(0.0).width_full()),
+    )
+        .v_stack()
+        .style(|s| s.size_full())
+}
+
+/// A single notification. Toasts can be dismissed.
+fn notification_view(notification: Notification, is_toast: bool) -> impl IntoView {
+    let Notification {
+        id,
+        level,
+        title,
+        body,
+        actions,
+        time,
+    } = notification;
+    let color = match level {
+        Level::Info => Color::GRAY,
+        Level::Warning => Color::rgb8(204, 136, 0),
+        Level::Error => Color::RED,
+    };
+    let time = time.format("%H:%M").to_string();
+
+    let header = (
+        label(move || title.clone()).style(|s| s.flex_grow(1.0)),
+        label(move || time.clone()).style(|s| s.color(Color::GRAY).padding_horiz(4.0)),
+        label(|| "×")
+            .on_click_stop(move |_| dismiss(id))
+            .style(move |s| s.color(Color::GRAY).apply_if(!is_toast, |s| s.hide())),
+    )
+        .h_stack()
+        .style(|s| s.items_center().width_full());
+
+    let buttons = actions
+        .into_iter()
+        .map(|action| {
+            let run = action.run.clone();
+            button(label(move || action.label.clone())).action(move || {
+                run();
+                dismiss(id);
+            })
+        })
+        .collect::<Vec<_>>()
+        .h_stack()
+        .style(|s| s.gap(4.0));
+
+    (
+        header,
+        label(move || body.clone()).style(|s| s.color(Color::GRAY)),
+        buttons,
+    )
+        .v_stack()
+        .style(move |s| {
+            s.width_full()
+                .padding(6.0)
+                .gap(2.0)
+                .border(1.0)
+                .border_left(4.0)
+                .border_color(color)
+                .background(Color::WHITE)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify() {
+        clear();
+        info("Copied", "MAP01.wad", Vec::new());
+        error(
+            "Failed to delete",
+            "MAP02.wad is protected",
+            vec![Action::new("Retry", || ())],
+        );
+
+        let toasts = toasts();
+        assert_eq!(toasts.len(), 2);
+        assert_eq!(toasts[1].level, Level::Error);
+        assert_eq!(toasts[1].actions[0].label, "Retry");
+
+        dismiss(toasts[0].id);
+        assert_eq!(super::toasts().len(), 1);
+        assert_eq!(history().len(), 2);
+    }
+}
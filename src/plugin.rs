@@ -0,0 +1,632 @@
+//! Plugins contributing commands, key bindings, context menu items and
+//! panels.
+//!
+//! A plugin implements [`Plugin`], and is [registered](register) before the
+//! app [launches](crate::app::launch). Its manifest declares everything it
+//! contributes:
+//!
+//! ```toml
+//! id = "wad-tools"
+//! name = "WAD Tools"
+//! version = "0.1.0"
+//! # or "on_startup"
+//! activation = "on_command"
+//!
+//! [[commands]]
+//! id = "wad-tools.validate"
+//! title = "Validate WAD"
+//! key = "ctrl+shift+v"
+//! context_menu = true
+//!
+//! [[panels]]
+//! id = "wad-tools.lumps"
+//! title = "Lumps"
+//! ```
+//!
+//! Plugins are activated when the app starts, or the first time one of their
+//! commands runs or panels opens, and deactivated when the app exits.
+
+use floem::AnyView;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::keymap::{KeyChord, Keymap};
+
+thread_local! {
+    static HOST: RefCell<PluginHost> = RefCell::new(PluginHost::default());
+}
+
+/// An extension of the app.
+pub trait Plugin {
+    /// The [manifest](Manifest) of the plugin, in TOML.
+    fn manifest(&self) -> &str;
+
+    /// Registers the handlers of the commands and panels in the manifest.
+    ///
+    /// The plugin host is busy while a plugin is activated, so plugins must
+    /// not run commands or open panels from here.
+    fn activate(&mut self, context: &mut Context);
+
+    /// Cleans up before the app exits.
+    fn deactivate(&mut self) {}
+}
+
+/// What a plugin contributes, and when it is activated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub activation: Activation,
+    pub commands: Vec<CommandInfo>,
+    pub panels: Vec<PanelInfo>,
+}
+
+impl Manifest {
+    /// Reads a manifest from a TOML string.
+    pub fn load_str(s: &str) -> Result<Manifest, Error> {
+        let doc = s
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| Error::Toml(err.to_string()))?;
+        let table = doc.as_table();
+
+        let activation = match table.get("activation").map(|item| item.as_str()) {
+            None | Some(Some("on_command")) => Activation::OnCommand,
+            Some(Some("on_startup")) => Activation::OnStartup,
+            Some(_) => return Err(Error::InvalidValue("activation".to_owned())),
+        };
+
+        let commands = tables(table, "commands")?
+            .into_iter()
+            .map(|command| {
+                let key = match command.get("key").map(|item| item.as_str()) {
+                    None => None,
+                    Some(Some(key)) => Some(
+                        KeyChord::parse(key)
+                            .ok_or(Error::InvalidValue("commands.key".to_owned()))?,
+                    ),
+                    Some(None) => return Err(Error::InvalidValue("commands.key".to_owned())),
+                };
+                let context_menu = match command.get("context_menu") {
+                    None => false,
+                    Some(item) => item
+                        .as_bool()
+                        .ok_or(Error::InvalidValue("commands.context_menu".to_owned()))?,
+                };
+
+                Ok(CommandInfo {
+                    id: string(command, "commands.id")?,
+                    title: string(command, "commands.title")?,
+                    key,
+                    context_menu,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let panels = tables(table, "panels")?
+            .into_iter()
+            .map(|panel| {
+                Ok(PanelInfo {
+                    id: string(panel, "panels.id")?,
+                    title: string(panel, "panels.title")?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Manifest {
+            id: string(table, "id")?,
+            name: string(table, "name")?,
+            version: string(table, "version")?,
+            activation,
+            commands,
+            panels,
+        })
+    }
+}
+
+/// Reads the string at `key` of `table`. `key` may be a dotted path, of
+/// which only the last part is looked up.
+fn string(table: &dyn toml_edit::TableLike, key: &str) -> Result<String, Error> {
+    let name = key.rsplit('.').next().unwrap_or(key);
+    table
+        .get(name)
+        .and_then(|item| item.as_str())
+        .map(str::to_owned)
+        .ok_or_else(|| Error::InvalidValue(key.to_owned()))
+}
+
+/// Reads the array of tables at `key` of `table`, which may be missing.
+fn tables<'a>(
+    table: &'a toml_edit::Table,
+    key: &str,
+) -> Result<Vec<&'a dyn toml_edit::TableLike>, Error> {
+    let Some(item) = table.get(key) else {
+        return Ok(Vec::new());
+    };
+    let array = item
+        .as_array_of_tables()
+        .ok_or_else(|| Error::InvalidValue(key.to_owned()))?;
+    Ok(array
+        .iter()
+        .map(|table| table as &dyn toml_edit::TableLike)
+        .collect())
+}
+
+/// When a plugin is activated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Activation {
+    /// When the app starts.
+    OnStartup,
+    /// The first time one of its commands runs or panels opens.
+    #[default]
+    OnCommand,
+}
+
+/// A command contributed by a plugin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandInfo {
+    pub id: String,
+    /// The name of the command shown in menus.
+    pub title: String,
+    /// The default key binding of the command, if any.
+    pub key: Option<KeyChord>,
+    /// `true` to offer the command in the context menu of the explorer.
+    pub context_menu: bool,
+}
+
+/// A panel contributed by a plugin, shown docked next to the explorer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanelInfo {
+    pub id: String,
+    /// The name of the panel shown in menus.
+    pub title: String,
+}
+
+/// What a command is run on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    /// The root of the pane the command was run from.
+    pub root: PathBuf,
+    /// The selected node, or the node the context menu was opened on.
+    pub path: Option<PathBuf>,
+}
+
+type CommandHandler = Rc<dyn Fn(&Invocation)>;
+type PanelBuilder = Rc<dyn Fn() -> AnyView>;
+
+/// The handlers a plugin registers when it is activated.
+#[derive(Default)]
+pub struct Context {
+    commands: HashMap<String, CommandHandler>,
+    panels: HashMap<String, PanelBuilder>,
+}
+
+impl Context {
+    /// Handles the command `id` with `handler`.
+    pub fn command(&mut self, id: impl Into<String>, handler: impl Fn(&Invocation) + 'static) {
+        self.commands.insert(id.into(), Rc::new(handler));
+    }
+
+    /// Builds the panel `id` with `view` each time it is opened.
+    pub fn panel(&mut self, id: impl Into<String>, view: impl Fn() -> AnyView + 'static) {
+        self.panels.insert(id.into(), Rc::new(view));
+    }
+}
+
+/// A registered plugin.
+struct Loaded {
+    manifest: Manifest,
+    plugin: Box<dyn Plugin>,
+    /// The handlers of the plugin, or `None` while it is not active.
+    context: Option<Context>,
+}
+
+/// The registered plugins, and the bindings of their commands.
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<Loaded>,
+    bindings: HashMap<KeyChord, String>,
+}
+
+impl PluginHost {
+    /// Registers `plugin`.
+    ///
+    /// Fails if its manifest cannot be read, it uses an id twice or reuses
+    /// the id of another plugin, command or panel, or it binds a key already
+    /// bound by another command or the default explorer keymap.
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) -> Result<(), Error> {
+        let manifest = Manifest::load_str(plugin.manifest())?;
+
+        if self.plugins.iter().any(|p| p.manifest.id == manifest.id) {
+            return Err(Error::Duplicate(manifest.id));
+        }
+        let mut ids = HashSet::new();
+        for command in &manifest.commands {
+            if !ids.insert(&command.id) || self.find_command(&command.id).is_some() {
+                return Err(Error::Duplicate(command.id.clone()));
+            }
+        }
+        let mut ids = HashSet::new();
+        for panel in &manifest.panels {
+            if !ids.insert(&panel.id) || self.find_panel(&panel.id).is_some() {
+                return Err(Error::Duplicate(panel.id.clone()));
+            }
+        }
+
+        // the explorer keymap is looked up first, so its keys cannot be used
+        let defaults = Keymap::default();
+        let mut keys = HashSet::new();
+        for command in &manifest.commands {
+            let Some(key) = &command.key else {
+                continue;
+            };
+            if !keys.insert(key) || self.bindings.contains_key(key) || defaults.get(key).is_some() {
+                return Err(Error::KeyTaken(command.id.clone()));
+            }
+        }
+
+        for command in &manifest.commands {
+            if let Some(key) = &command.key {
+                self.bindings.insert(key.clone(), command.id.clone());
+            }
+        }
+        self.plugins.push(Loaded {
+            manifest,
+            plugin,
+            context: None,
+        });
+        Ok(())
+    }
+
+    /// Activates the plugins activated on startup.
+    pub fn start(&mut self) {
+        for loaded in &mut self.plugins {
+            if loaded.manifest.activation == Activation::OnStartup {
+                loaded.activate();
+            }
+        }
+    }
+
+    /// Deactivates every active plugin.
+    pub fn stop(&mut self) {
+        for loaded in &mut self.plugins {
+            if loaded.context.take().is_some() {
+                loaded.plugin.deactivate();
+            }
+        }
+    }
+
+    /// The manifests of the registered plugins.
+    pub fn manifests(&self) -> impl Iterator<Item = &Manifest> {
+        self.plugins.iter().map(|loaded| &loaded.manifest)
+    }
+
+    /// Gets the command bound to `chord`.
+    pub fn lookup(&self, chord: &KeyChord) -> Option<&str> {
+        self.bindings.get(chord).map(String::as_str)
+    }
+
+    /// Gets the handler of the command `id`, activating its plugin if needed.
+    fn command(&mut self, id: &str) -> Result<CommandHandler, Error> {
+        let index = self
+            .find_command(id)
+            .ok_or_else(|| Error::UnknownCommand(id.to_owned()))?;
+        self.plugins[index]
+            .activate()
+            .commands
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::NotHandled(id.to_owned()))
+    }
+
+    /// Gets the builder of the panel `id`, activating its plugin if needed.
+    fn panel(&mut self, id: &str) -> Result<PanelBuilder, Error> {
+        let index = self
+            .find_panel(id)
+            .ok_or_else(|| Error::UnknownPanel(id.to_owned()))?;
+        self.plugins[index]
+            .activate()
+            .panels
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::NotHandled(id.to_owned()))
+    }
+
+    /// The index of the plugin contributing the command `id`.
+    fn find_command(&self, id: &str) -> Option<usize> {
+        self.plugins.iter().position(|loaded| {
+            loaded
+                .manifest
+                .commands
+                .iter()
+                .any(|command| command.id == id)
+        })
+    }
+
+    /// The index of the plugin contributing the panel `id`.
+    fn find_panel(&self, id: &str) -> Option<usize> {
+        self.plugins
+            .iter()
+            .position(|loaded| loaded.manifest.panels.iter().any(|panel| panel.id == id))
+    }
+}
+
+impl Loaded {
+    /// Activates the plugin if it is not active yet.
+    fn activate(&mut self) -> &Context {
+        self.context.get_or_insert_with(|| {
+            let mut context = Context::default();
+            self.plugin.activate(&mut context);
+            context
+        })
+    }
+}
+
+/// Registers `plugin` with the app.
+pub fn register(plugin: Box<dyn Plugin>) -> Result<(), Error> {
+    HOST.with(|host| host.borrow_mut().register(plugin))
+}
+
+/// Activates the plugins activated on startup.
+pub fn start() {
+    HOST.with(|host| host.borrow_mut().start());
+}
+
+/// Deactivates every active plugin.
+pub fn stop() {
+    HOST.with(|host| host.borrow_mut().stop());
+}
+
+/// The commands of every plugin.
+pub fn commands() -> Vec<CommandInfo> {
+    HOST.with(|host| {
+        host.borrow()
+            .manifests()
+            .flat_map(|manifest| manifest.commands.iter().cloned())
+            .collect()
+    })
+}
+
+/// The panels of every plugin.
+pub fn panels() -> Vec<PanelInfo> {
+    HOST.with(|host| {
+        host.borrow()
+            .manifests()
+            .flat_map(|manifest| manifest.panels.iter().cloned())
+            .collect()
+    })
+}
+
+/// Gets the plugin command bound to `chord`.
+pub fn lookup(chord: &KeyChord) -> Option<String> {
+    HOST.with(|host| host.borrow().lookup(chord).map(str::to_owned))
+}
+
+/// Warns about the plugin commands whose key is bound in `keymap`, as the
+/// explorer keymap is looked up first and the binding never reaches them.
+pub fn warn_shadowed(keymap: &Keymap) {
+    HOST.with(|host| {
+        for (chord, id) in &host.borrow().bindings {
+            if let Some(command) = keymap.get(chord) {
+                tracing::warn!(
+                    plugin_command = %id,
+                    command = command.id(),
+                    "plugin key binding is shadowed by the keymap"
+                );
+            }
+        }
+    });
+}
+
+/// Runs the plugin command `id`. Problems running it are reported.
+pub fn run(id: &str, invocation: &Invocation) {
+    crate::logging::ran_command(id);
+    // the handler may use the host itself
+    match HOST.with(|host| host.borrow_mut().command(id)) {
+        Ok(handler) => handler(invocation),
+        Err(err) => {
+            let body = format!("{}: {}", id, err);
+            crate::notify::error(
+                crate::locale::tr("Failed to run plugin command"),
+                body,
+                Vec::new(),
+            );
+        }
+    }
+}
+
+/// Builds the plugin panel `id`.
+pub fn panel_view(id: &str) -> Result<AnyView, Error> {
+    let builder = HOST.with(|host| host.borrow_mut().panel(id))?;
+    Ok(builder())
+}
+
+/// An error that can occur while registering or using a plugin.
+#[derive(Debug)]
+pub enum Error {
+    /// The manifest is not valid TOML.
+    Toml(String),
+    /// A field of the manifest is missing, or has an invalid value.
+    InvalidValue(String),
+    /// A plugin, command or panel id is already taken.
+    Duplicate(String),
+    /// The key of a command is already bound.
+    KeyTaken(String),
+    /// No plugin contributes a command.
+    UnknownCommand(String),
+    /// No plugin contributes a panel.
+    UnknownPanel(String),
+    /// A plugin contributes a command or panel, but did not register a
+    /// handler for it when activated.
+    NotHandled(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Toml(err) => f.write_str(err),
+            Error::InvalidValue(key) => write!(f, "invalid value for {}", key),
+            Error::Duplicate(id) => write!(f, "{} is already registered", id),
+            Error::KeyTaken(id) => write!(f, "the key of {} is already bound", id),
+            Error::UnknownCommand(id) => write!(f, "unknown command {}", id),
+            Error::UnknownPanel(id) => write!(f, "unknown panel {}", id),
+            Error::NotHandled(id) => write!(f, "{} has no handler", id),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    const MANIFEST: &str = r#"
+        id = "wad-tools"
+        name = "WAD Tools"
+        version = "0.1.0"
+
+        [[commands]]
+        id = "wad-tools.validate"
+        title = "Validate WAD"
+        key = "ctrl+shift+v"
+        context_menu = true
+    "#;
+
+    struct WadTools {
+        activations: Rc<Cell<u32>>,
+        runs: Rc<Cell<u32>>,
+    }
+
+    impl Plugin for WadTools {
+        fn manifest(&self) -> &str {
+            MANIFEST
+        }
+
+        fn activate(&mut self, context: &mut Context) {
+            self.activations.set(self.activations.get() + 1);
+            let runs = self.runs.clone();
+            context.command("wad-tools.validate", move |_| runs.set(runs.get() + 1));
+        }
+    }
+
+    #[test]
+    fn test_manifest() {
+        let manifest = Manifest::load_str(MANIFEST).unwrap();
+        assert_eq!(manifest.activation, Activation::OnCommand);
+        assert_eq!(
+            manifest.commands,
+            [CommandInfo {
+                id: "wad-tools.validate".to_owned(),
+                title: "Validate WAD".to_owned(),
+                key: KeyChord::parse("ctrl+shift+v"),
+                context_menu: true,
+            }]
+        );
+        assert!(manifest.panels.is_empty());
+
+        assert!(matches!(
+            Manifest::load_str("id = \"wad-tools\"\nactivation = \"never\""),
+            Err(Error::InvalidValue(key)) if key == "activation"
+        ));
+    }
+
+    #[test]
+    fn test_activation() {
+        let activations = Rc::new(Cell::new(0));
+        let runs = Rc::new(Cell::new(0));
+        let plugin = || {
+            Box::new(WadTools {
+                activations: activations.clone(),
+                runs: runs.clone(),
+            })
+        };
+
+        let mut host = PluginHost::default();
+        host.register(plugin()).unwrap();
+        assert!(matches!(host.register(plugin()), Err(Error::Duplicate(_))));
+
+        // activated lazily, once
+        host.start();
+        assert_eq!(activations.get(), 0);
+        let chord = KeyChord::parse("ctrl+shift+v").unwrap();
+        let id = host.lookup(&chord).unwrap().to_owned();
+        let invocation = Invocation {
+            root: PathBuf::from("/var/doom"),
+            path: None,
+        };
+        host.command(&id).unwrap()(&invocation);
+        host.command(&id).unwrap()(&invocation);
+        assert_eq!((activations.get(), runs.get()), (1, 2));
+
+        assert!(matches!(
+            host.command("wad-tools.pack"),
+            Err(Error::UnknownCommand(_))
+        ));
+    }
+
+    /// A plugin with nothing but a manifest.
+    struct Manifested(String);
+
+    impl Plugin for Manifested {
+        fn manifest(&self) -> &str {
+            &self.0
+        }
+
+        fn activate(&mut self, _context: &mut Context) {}
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let register = |manifest: String| {
+            let mut host = PluginHost::default();
+            host.register(Box::new(Manifested(MANIFEST.to_owned())))
+                .unwrap();
+            host.register(Box::new(Manifested(manifest)))
+        };
+        let header = "id = \"map-tools\"\nname = \"Map Tools\"\nversion = \"0.1.0\"\n";
+        let manifest = |body: &str| format!("{}{}", header, body);
+
+        let command = |id: &str, key: &str| {
+            format!(
+                "[[commands]]\nid = \"{}\"\ntitle = \"Command\"\nkey = \"{}\"\n",
+                id, key
+            )
+        };
+        let panel = |id: &str| format!("[[panels]]\nid = \"{}\"\ntitle = \"Panel\"\n", id);
+
+        assert!(register(manifest(&command("map-tools.build", "ctrl+shift+b"))).is_ok());
+
+        // ids used twice in the same manifest
+        let commands = command("map-tools.build", "ctrl+shift+b")
+            + &command("map-tools.build", "ctrl+shift+m");
+        assert!(matches!(
+            register(manifest(&commands)),
+            Err(Error::Duplicate(id)) if id == "map-tools.build"
+        ));
+        let panels = panel("map-tools.things") + &panel("map-tools.things");
+        assert!(matches!(
+            register(manifest(&panels)),
+            Err(Error::Duplicate(id)) if id == "map-tools.things"
+        ));
+
+        // keys bound twice, by another plugin, or by the explorer
+        let commands =
+            command("map-tools.build", "ctrl+shift+b") + &command("map-tools.run", "ctrl+shift+b");
+        assert!(matches!(
+            register(manifest(&commands)),
+            Err(Error::KeyTaken(id)) if id == "map-tools.run"
+        ));
+        for key in ["ctrl+shift+v", "ctrl+b"] {
+            assert!(matches!(
+                register(manifest(&command("map-tools.build", key))),
+                Err(Error::KeyTaken(id)) if id == "map-tools.build"
+            ));
+        }
+    }
+}
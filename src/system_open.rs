@@ -0,0 +1,73 @@
+//! Opening files outside of Mobius.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Opens `path` with the default application of the system.
+pub fn open(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        // the empty argument is the title of the window `start` opens
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    spawn(command.arg(path))
+}
+
+/// Opens `path` with `program`.
+pub fn open_with(program: &str, path: &Path) -> io::Result<()> {
+    spawn(Command::new(program).arg(path))
+}
+
+/// Shows `path` in the file manager of the system, selected if the file
+/// manager supports it.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    if cfg!(windows) {
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(path);
+        return spawn(Command::new("explorer").arg(select));
+    }
+    if cfg!(target_os = "macos") {
+        return spawn(Command::new("open").arg("-R").arg(path));
+    }
+
+    // ask a freedesktop file manager to select the file, falling back to
+    // opening its directory
+    let mut uri = std::ffi::OsString::from("array:string:file://");
+    uri.push(path);
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--type=method_call",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(uri)
+        .arg("string:")
+        .status()
+        .is_ok_and(|status| status.success());
+    if shown {
+        return Ok(());
+    }
+
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    spawn(Command::new("xdg-open").arg(dir))
+}
+
+/// Starts `command` without waiting for it to finish.
+fn spawn(command: &mut Command) -> io::Result<()> {
+    let mut child = command.spawn()?;
+    // reap the process once it exits
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,278 @@
+//! Workspaces, each shown in its own window.
+
+use floem::action::set_window_title;
+use floem::event::{Event, EventListener};
+use floem::menu::{Menu, MenuItem};
+use floem::prelude::*;
+use floem::reactive::create_effect;
+
+use std::path::{Path, PathBuf};
+
+use crate::file_explorer::badges;
+use crate::file_explorer::state::ExplorerState;
+use crate::file_explorer::view::{confirm_view, file_explorer_view};
+use crate::git::{branch_menu, source_control_view, SourceControl};
+use crate::locale::{self, tr};
+use crate::notify::{self, history_view, toasts_view};
+use crate::plugin;
+
+/// The state of a single open project.
+#[derive(Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub explorer: ExplorerState,
+    /// The path the second pane opens at when the explorer is split.
+    pub second_root: PathBuf,
+}
+
+impl Workspace {
+    /// Opens the project at `root`.
+    ///
+    /// The second pane shows the same project, unless `second_root` is
+    /// given.
+    pub fn new(root: impl Into<PathBuf>, second_root: Option<PathBuf>) -> Workspace {
+        // show a real name for roots like `.`
+        let root = canonicalize(root.into());
+        let second_root = second_root.map(canonicalize);
+        crate::logging::opened_workspace(&root);
+        Workspace {
+            explorer: ExplorerState::new(root.clone()),
+            second_root: second_root.unwrap_or_else(|| root.clone()),
+            root,
+        }
+    }
+}
+
+/// Badges the files changed in source control in every pane, again whenever
+/// the status is read or a pane finishes scanning.
+fn show_changes(state: ExplorerState, scm: SourceControl) {
+    create_effect(move |_| {
+        let changed = scm.toplevel.with(|toplevel| {
+            let Some(toplevel) = toplevel else {
+                return Vec::new();
+            };
+            scm.changes.with(|changes| {
+                changes
+                    .iter()
+                    .map(|change| toplevel.join(&change.path))
+                    .collect::<Vec<_>>()
+            })
+        });
+
+        for pane in state.panes.get() {
+            // nodes are still being added
+            if pane.scanning.get() {
+                continue;
+            }
+            pane.tree
+                .update(|tree| badges::set_modified(tree, &changed));
+        }
+    });
+}
+
+/// Makes `path` absolute, if it exists.
+fn canonicalize(path: PathBuf) -> PathBuf {
+    path.canonicalize().unwrap_or(path)
+}
+
+/// Opens the project at `root` in a new window.
+pub fn open_window(root: impl Into<PathBuf>) {
+    let root = root.into();
+    floem::new_window(move |_| workspace_view(Workspace::new(root, None)), None);
+}
+
+/// The contents of a workspace window.
+pub fn workspace_view(workspace: Workspace) -> impl IntoView {
+    let Workspace {
+        root,
+        explorer: state,
+        second_root,
+    } = workspace;
+    create_effect(move |_| set_window_title(window_title(state)));
+    // the plugin panel docked next to the explorer, if any
+    let docked = create_rw_signal(None::<String>);
+    let scm = SourceControl::new(root);
+    show_changes(state, scm);
+    let scm_open = create_rw_signal(false);
+    let notifications_open = create_rw_signal(false);
+
+    let toolbar = (
+        button(label(move || {
+            if state.is_split() {
+                tr("Unsplit")
+            } else {
+                tr("Split")
+            }
+        }))
+        .action(move || state.toggle_split(second_root.clone())),
+        button(label(|| tr("Swap panes")))
+            .action(move || state.swap_panes())
+            .disabled(move || !state.is_split()),
+        button(label(|| tr("Language"))).popout_menu(language_menu),
+        button(label(|| tr("Source Control"))).action(move || {
+            if !scm_open.get_untracked() {
+                scm.refresh();
+            }
+            scm_open.update(|open| *open = !*open);
+        }),
+        button(label(|| tr("Notifications")))
+            .action(move || notifications_open.update(|open| *open = !*open)),
+        button(label(|| tr("Open Log"))).action(open_log),
+        button(label(|| tr("Reload Settings"))).action(move || state.reload_settings()),
+        button(label(|| tr("Panels")))
+            .popout_menu(move || panels_menu(docked))
+            .style(|s| s.apply_if(plugin::panels().is_empty(), |s| s.hide())),
+    )
+        .h_stack()
+        .style(|s| s.gap(4.0).padding_bottom(4.0));
+
+    let panes = dyn_container(
+        move || state.panes.get(),
+        move |panes| {
+            h_stack_from_iter(
+                panes
+                    .into_iter()
+                    .map(|pane| file_explorer_view(state, pane)),
+            )
+            .style(|s| s.gap(4.0).height_full())
+        },
+    )
+    .style(|s| s.flex_grow(1.0).min_height(0.0));
+
+    let dock = dyn_container(
+        move || docked.get(),
+        move |id| {
+            let Some(id) = id else {
+                return empty().into_any();
+            };
+            match plugin::panel_view(&id) {
+                Ok(view) => (
+                    button(label(|| tr("Close"))).action(move || docked.set(None)),
+                    view.style(|s| s.flex_grow(1.0).min_height(0.0)),
+                )
+                    .v_stack()
+                    .style(|s| s.width(240.0).height_full().border(1.0))
+                    .into_any(),
+                Err(err) => {
+                    let body = format!("{}: {}", id, err);
+                    notify::error(tr("Failed to open panel"), body, Vec::new());
+                    empty().into_any()
+                }
+            }
+        },
+    );
+
+    let scm_dock = source_control_view(scm).style(move |s| {
+        s.width(260.0)
+            .height_full()
+            .border(1.0)
+            .apply_if(!scm_open.get(), |s| s.hide())
+    });
+
+    let notifications_dock = history_view().style(move |s| {
+        s.width(260.0)
+            .height_full()
+            .border(1.0)
+            .apply_if(!notifications_open.get(), |s| s.hide())
+    });
+
+    let body = (panes, dock, scm_dock, notifications_dock)
+        .h_stack()
+        .style(|s| s.gap(4.0).flex_grow(1.0).min_height(0.0));
+
+    // the branch, which can be switched from here
+    let status_bar = button(label(move || {
+        format!("⎇ {}", scm.branch.get().unwrap_or_default())
+    }))
+    .popout_menu(move || branch_menu(scm))
+    .style(move |s| s.apply_if(scm.branch.with(Option::is_none), |s| s.hide()));
+
+    let explorer = container(
+        (toolbar, body, status_bar)
+            .v_stack()
+            .style(|s| s.height_full().gap(4.0)),
+    )
+    .style(|s| {
+        s.size(100.pct(), 100.pct())
+            .padding_vert(20.0)
+            .flex_col()
+            .items_center()
+    });
+
+    (explorer, confirm_view(state), toasts_view())
+        .v_stack()
+        .on_event_stop(EventListener::DroppedFile, move |e| {
+            if let Event::DroppedFile(e) = e {
+                state.open_dropped(&e.path);
+            }
+        })
+        .style(|s| s.size_full())
+}
+
+/// Opens the newest log file with the default application of the system.
+pub fn open_log() {
+    let Some(path) = crate::logging::latest_log() else {
+        notify::warn(tr("There is no log file to open"), "", Vec::new());
+        return;
+    };
+    if let Err(err) = crate::system_open::open(&path) {
+        let body = format!("{}: {}", path.display(), err);
+        notify::error(tr("Failed to open log"), body, Vec::new());
+    }
+}
+
+/// A menu docking one of the plugin panels next to the explorer.
+fn panels_menu(docked: RwSignal<Option<String>>) -> Menu {
+    plugin::panels()
+        .into_iter()
+        .fold(Menu::new(""), |menu, panel| {
+            let name = if docked.with_untracked(|id| id.as_ref() == Some(&panel.id)) {
+                format!("✓ {}", tr(&panel.title))
+            } else {
+                tr(&panel.title)
+            };
+            menu.entry(MenuItem::new(name).action(move || docked.set(Some(panel.id.clone()))))
+        })
+}
+
+/// A menu switching between the languages there are bundles for.
+fn language_menu() -> Menu {
+    let current = locale::language();
+    locale::languages()
+        .into_iter()
+        .fold(Menu::new(""), |menu, language| {
+            let name = if language == current {
+                format!("✓ {}", language)
+            } else {
+                language.clone()
+            };
+            menu.entry(MenuItem::new(name).action(move || locale::set_language(&language)))
+        })
+}
+
+/// The title of the window, like `MAPINFO.txt — doom — mobius`.
+///
+/// Names the file previewed in the first pane, or else the node selected in
+/// it.
+fn window_title(state: ExplorerState) -> String {
+    let Some(pane) = state.panes.with(|panes| panes.first().copied()) else {
+        return "mobius".to_owned();
+    };
+
+    let name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    };
+    let root = pane.tree.with(|tree| tree.root().path().to_owned());
+    let file = pane
+        .preview
+        .get()
+        .or_else(|| pane.selected.get())
+        .filter(|path| *path != root);
+
+    match file {
+        Some(file) => format!("{} — {} — mobius", name(&file), name(&root)),
+        None => format!("{} — mobius", name(&root)),
+    }
+}